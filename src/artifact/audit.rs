@@ -0,0 +1,108 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use log::{info, warn, error};
+
+use crate::artifact::internal_artifact::{ArtifactSlot, ArtifactStatName, InternalArtifact, expected_sub_stat_count};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AuditSeverity {
+    // unusual, but possible in game
+    Warning,
+    // cannot exist in game, the record was misread
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditFinding {
+    pub severity: AuditSeverity,
+    // position in the audited slice
+    pub index: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AuditFinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{} {:?}: {}", self.index, self.severity, self.message)
+    }
+}
+
+fn is_valid_main_stat(slot: &ArtifactSlot, name: &ArtifactStatName) -> bool {
+    use ArtifactStatName::*;
+
+    match slot {
+        ArtifactSlot::Flower => *name == Hp,
+        ArtifactSlot::Feather => *name == Atk,
+        ArtifactSlot::Sand => matches!(name,
+            AtkPercentage | HpPercentage | DefPercentage | ElementalMastery | Recharge),
+        ArtifactSlot::Goblet => matches!(name,
+            AtkPercentage | HpPercentage | DefPercentage | ElementalMastery
+            | ElectroBonus | PyroBonus | HydroBonus | CryoBonus | AnemoBonus | GeoBonus | PhysicalBonus),
+        ArtifactSlot::Head => matches!(name,
+            AtkPercentage | HpPercentage | DefPercentage | ElementalMastery
+            | Critical | CriticalDamage | HealingBonus),
+    }
+}
+
+fn max_level(star: u32) -> u32 {
+    match star {
+        1 | 2 => 4,
+        3 => 12,
+        4 => 16,
+        _ => 20,
+    }
+}
+
+pub fn audit(items: &[InternalArtifact]) -> Vec<AuditFinding> {
+    let mut findings: Vec<AuditFinding> = Vec::new();
+    let mut seen: HashSet<&InternalArtifact> = HashSet::new();
+
+    for (index, art) in items.iter().enumerate() {
+        let mut report = |severity: AuditSeverity, message: String| {
+            findings.push(AuditFinding { severity, index, message });
+        };
+
+        if !seen.insert(art) {
+            report(AuditSeverity::Warning, String::from("duplicate artifact"));
+        }
+
+        if !is_valid_main_stat(&art.slot, &art.main_stat.name) {
+            report(AuditSeverity::Error, format!("{} cannot be the main stat of {}", art.main_stat.name, art.slot));
+        }
+
+        if art.star < 1 || art.star > 5 {
+            report(AuditSeverity::Error, format!("invalid star {}", art.star));
+        } else if art.level > max_level(art.star) {
+            report(AuditSeverity::Error, format!("level {} exceeds the max level of a {} star artifact", art.level, art.star));
+        }
+
+        let (min_sub, max_sub) = expected_sub_stat_count(art.star, art.level);
+        let sub_count = art.sub_stat_count();
+        if sub_count < min_sub || sub_count > max_sub {
+            report(AuditSeverity::Warning, format!("expected {}~{} sub stats but got {}", min_sub, max_sub, sub_count));
+        }
+
+        let subs = [&art.sub_stat_1, &art.sub_stat_2, &art.sub_stat_3, &art.sub_stat_4];
+        let mut sub_names: HashSet<&ArtifactStatName> = HashSet::new();
+        for sub in subs.iter().filter_map(|x| x.as_ref()) {
+            if sub.name == art.main_stat.name {
+                report(AuditSeverity::Error, format!("sub stat {} is the same as the main stat", sub.name));
+            }
+            if !sub_names.insert(&sub.name) {
+                report(AuditSeverity::Error, format!("sub stat {} appears twice", sub.name));
+            }
+        }
+    }
+
+    findings
+}
+
+pub fn log_audit(items: &[InternalArtifact], findings: &[AuditFinding]) {
+    for finding in findings.iter() {
+        match finding.severity {
+            AuditSeverity::Warning => warn!("audit {}, {:?}", finding, items[finding.index]),
+            AuditSeverity::Error => error!("audit {}, {:?}", finding, items[finding.index]),
+        }
+    }
+    info!("audit: {} findings in {} artifacts", findings.len(), items.len());
+}
\ No newline at end of file
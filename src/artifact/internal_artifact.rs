@@ -1,8 +1,11 @@
 use regex::Regex;
 use std::hash::{Hash, Hasher};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
 use edit_distance;
 
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ArtifactStatName {
     HealingBonus,
     CriticalDamage,
@@ -24,7 +27,7 @@ pub enum ArtifactStatName {
     PhysicalBonus,
 }
 
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ArtifactSlot {
     Flower,
     Feather,
@@ -33,7 +36,7 @@ pub enum ArtifactSlot {
     Head,
 }
 
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ArtifactSetName {
     ArchaicPetra,
     HeartOfDepth,
@@ -72,6 +75,14 @@ pub enum ArtifactSetName {
     ShimenawaReminiscence,
 }
 
+// set effects in zh_cn, keyed by the number of pieces required
+#[derive(Debug, Clone)]
+pub struct SetBonus {
+    pub effects: &'static [(u32, &'static str)],
+    // stats that the set is usually paired with, e.g. for scoring
+    pub stats: &'static [ArtifactStatName],
+}
+
 #[derive(Debug, Clone)]
 pub struct ArtifactStat {
     pub name: ArtifactStatName,
@@ -95,7 +106,7 @@ pub struct InternalArtifact {
 impl Hash for ArtifactStat {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.name.hash(state);
-        let v = (self.value * 1000.0) as i32;
+        let v = (self.value * 1000.0).round() as i32;
         v.hash(state);
     }
 }
@@ -106,8 +117,8 @@ impl PartialEq for ArtifactStat {
             return false;
         }
 
-        let v1 = (self.value * 1000.0) as i32;
-        let v2 = (other.value * 1000.0) as i32;
+        let v1 = (self.value * 1000.0).round() as i32;
+        let v2 = (other.value * 1000.0).round() as i32;
 
         v1 == v2
     }
@@ -115,7 +126,54 @@ impl PartialEq for ArtifactStat {
 
 impl Eq for ArtifactStat {}
 
+impl PartialOrd for ArtifactStat {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// consistent with PartialEq, values are compared at the same precision
+impl Ord for ArtifactStat {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let v1 = (self.value * 1000.0).round() as i32;
+        let v2 = (other.value * 1000.0).round() as i32;
+
+        self.name.cmp(&other.name).then(v1.cmp(&v2))
+    }
+}
+
 impl ArtifactStatName {
+    // percentage stats are stored as fractions, e.g. 10.5% is 0.105
+    pub fn is_percentage(&self) -> bool {
+        !matches!(self, ArtifactStatName::Atk
+            | ArtifactStatName::Hp
+            | ArtifactStatName::Def
+            | ArtifactStatName::ElementalMastery)
+    }
+
+    pub fn all() -> &'static [ArtifactStatName] {
+        &[
+            ArtifactStatName::HealingBonus,
+            ArtifactStatName::CriticalDamage,
+            ArtifactStatName::Critical,
+            ArtifactStatName::Atk,
+            ArtifactStatName::AtkPercentage,
+            ArtifactStatName::ElementalMastery,
+            ArtifactStatName::Recharge,
+            ArtifactStatName::HpPercentage,
+            ArtifactStatName::Hp,
+            ArtifactStatName::DefPercentage,
+            ArtifactStatName::Def,
+            ArtifactStatName::ElectroBonus,
+            ArtifactStatName::PyroBonus,
+            ArtifactStatName::HydroBonus,
+            ArtifactStatName::CryoBonus,
+            ArtifactStatName::AnemoBonus,
+            ArtifactStatName::GeoBonus,
+            ArtifactStatName::PhysicalBonus,
+        ]
+    }
+
     pub fn from_zh_cn(name: &str, is_percentage: bool) -> Option<ArtifactStatName> {
         match name {
             "治疗加成" => Some(ArtifactStatName::HealingBonus),
@@ -165,41 +223,103 @@ impl ArtifactStat {
     }
 }
 
+impl InternalArtifact {
+    // set, slot, star and main stat, then the remaining fields, so that
+    // an unchanged inventory always exports in the same order
+    pub fn export_order(&self, other: &InternalArtifact) -> Ordering {
+        self.set_name.cmp(&other.set_name)
+            .then(self.slot.cmp(&other.slot))
+            .then(self.star.cmp(&other.star))
+            .then(self.main_stat.cmp(&other.main_stat))
+            .then(self.level.cmp(&other.level))
+            .then(self.sub_stat_1.cmp(&other.sub_stat_1))
+            .then(self.sub_stat_2.cmp(&other.sub_stat_2))
+            .then(self.sub_stat_3.cmp(&other.sub_stat_3))
+            .then(self.sub_stat_4.cmp(&other.sub_stat_4))
+            .then(self.equip.cmp(&other.equip))
+    }
+
+    // the artifact itself, for comparing with exports that do not record
+    // who equips it
+    pub fn without_equip(&self) -> InternalArtifact {
+        InternalArtifact {
+            equip: None,
+            ..self.clone()
+        }
+    }
+
+    pub fn sub_stat_count(&self) -> u32 {
+        [&self.sub_stat_1, &self.sub_stat_2, &self.sub_stat_3, &self.sub_stat_4]
+            .iter()
+            .filter(|x| x.is_some())
+            .count() as u32
+    }
+}
+
+// the (min, max) number of sub stats an artifact of this star and level can have.
+// an artifact starts with star - 2 or star - 1 sub stats, and every 4 levels
+// adds one until there are 4
+pub fn expected_sub_stat_count(star: u32, level: u32) -> (u32, u32) {
+    let initial_min = star.saturating_sub(2);
+    let initial_max = star.saturating_sub(1);
+
+    ((initial_min + level / 4).min(4), (initial_max + level / 4).min(4))
+}
+
+// characters the model confuses with each other, mapped to the form used in the name tables
+const HOMOGLYPHS: [(char, char); 1] = [
+    ('壁', '璧'),
+];
+
+pub fn normalize_homoglyph(s: &str) -> String {
+    s.chars().map(|c| {
+        for &(from, to) in HOMOGLYPHS.iter() {
+            if c == from {
+                return to;
+            }
+        }
+        c
+    }).collect()
+}
+
+// every artifact piece name, both from_zh_cn tables are expected to cover exactly these
+const ALL_ARTIFACT_CHS: &[&str] = &[
+    "磐陀裂生之花", "嵯峨群峰之翼", "星罗圭璧之晷", "巉岩琢塑之樽", "不动玄石之相",
+    "历经风雪的思念", "摧冰而行的执望", "冰雪故园的终期", "遍结寒霜的傲骨", "破冰踏雪的回音",
+    "染血的铁之心", "染血的黑之羽", "骑士染血之时", "染血骑士之杯", "染血的铁假面",
+    "魔女的炎之花", "魔女常燃之羽", "魔女破灭之时", "魔女的心之火", "焦灼的魔女帽",
+    "角斗士的留恋", "角斗士的归宿", "角斗士的希冀", "角斗士的酣醉", "角斗士的凯旋",
+    "饰金胸花", "追忆之风", "坚铜罗盘", "沉波之盏", "酒渍船帽",
+    "渡火者的决绝", "渡火者的解脱", "渡火者的煎熬", "渡火者的醒悟", "渡火者的智慧",
+    "远方的少女之心", "少女飘摇的思念", "少女苦短的良辰", "少女片刻的闲暇", "少女易逝的芳颜",
+    "宗室之花", "宗室之翎", "宗室时计", "宗室银瓮", "宗室面具",
+    "夏祭之花", "夏祭终末", "夏祭之刻", "夏祭水玉", "夏祭之面",
+    "平雷之心", "平雷之羽", "平雷之刻", "平雷之器", "平雷之冠",
+    "雷鸟的怜悯", "雷灾的孑遗", "雷霆的时计", "降雷的凶兆", "唤雷的头冠",
+    "野花记忆的绿野", "猎人青翠的箭羽", "翠绿猎人的笃定", "翠绿猎人的容器", "翠绿的猎人之冠",
+    "乐团的晨光", "琴师的箭羽", "终幕的时计", "终末的时计", "吟游者之壶", "指挥的礼帽",
+    "战狂的蔷薇", "战狂的翎羽", "战狂的时计", "战狂的骨杯", "战狂的鬼面",
+    "勇士的勋章", "勇士的期许", "勇士的坚毅", "勇士的壮行", "勇士的冠冕",
+    "守护之花", "守护徽印", "守护座钟", "守护之皿", "守护束带",
+    "流放者之花", "流放者之羽", "流放者怀表", "流放者之杯", "流放者头冠",
+    "赌徒的胸花", "赌徒的羽饰", "赌徒的怀表", "赌徒的骰盅", "赌徒的耳环",
+    "教官的胸花", "教官的羽饰", "教官的怀表", "教官的茶杯", "教官的帽子",
+    "武人的红花", "武人的羽饰", "武人的水漏", "武人的酒杯", "武人的头巾",
+    "祭水礼冠", "祭火礼冠", "祭雷礼冠", "祭冰礼冠",
+    "故人之心", "归乡之羽", "逐光之石", "异国之盏", "感别之冠",
+    "学士的书签", "学士的羽笔", "学士的时钟", "学士的墨杯", "学士的镜片",
+    "奇迹之花", "奇迹之羽", "奇迹之沙", "奇迹之杯", "奇迹耳坠",
+    "冒险家之花", "冒险家尾羽", "冒险家怀表", "冒险家金杯", "冒险家头带",
+    "幸运儿绿花", "幸运儿鹰羽", "幸运儿沙漏", "幸运儿之杯", "幸运儿银冠",
+    "游医的银莲", "游医的枭羽", "游医的怀钟", "游医的药壶", "游医的方巾",
+    "勋绩之花", "昭武翎羽", "金铜时晷", "盟誓金爵", "将帅兜鍪",
+    "无垢之花", "贤医之羽", "停摆之刻", "超越之盏", "嗤笑之面",
+    "明威之镡", "切落之羽", "雷云之笼", "绯花之壶", "华饰之兜",
+    "羁缠之花", "思忆之矢", "朝露之时", "祈望之心", "无常之面",
+];
+
 pub fn get_real_artifact_name_chs(raw: &str) -> Option<String> {
-    let all_artifact_chs = [
-        "磐陀裂生之花", "嵯峨群峰之翼", "星罗圭壁之晷", "星罗圭璧之晷", "巉岩琢塑之樽", "不动玄石之相",
-        "历经风雪的思念", "摧冰而行的执望", "冰雪故园的终期", "遍结寒霜的傲骨", "破冰踏雪的回音",
-        "染血的铁之心", "染血的黑之羽", "骑士染血之时", "染血骑士之杯", "染血的铁假面",
-        "魔女的炎之花", "魔女常燃之羽", "魔女破灭之时", "魔女的心之火", "焦灼的魔女帽",
-        "角斗士的留恋", "角斗士的归宿", "角斗士的希冀", "角斗士的酣醉", "角斗士的凯旋",
-        "饰金胸花", "追忆之风", "坚铜罗盘", "沉波之盏", "酒渍船帽",
-        "渡火者的决绝", "渡火者的解脱", "渡火者的煎熬", "渡火者的醒悟", "渡火者的智慧",
-        "远方的少女之心", "少女飘摇的思念", "少女苦短的良辰", "少女片刻的闲暇", "少女易逝的芳颜",
-        "宗室之花", "宗室之翎", "宗室时计", "宗室银瓮", "宗室面具",
-        "夏祭之花", "夏祭终末", "夏祭之刻", "夏祭水玉", "夏祭之面",
-        "平雷之心", "平雷之羽", "平雷之刻", "平雷之器", "平雷之冠",
-        "雷鸟的怜悯", "雷灾的孑遗", "雷霆的时计", "降雷的凶兆", "唤雷的头冠",
-        "野花记忆的绿野", "猎人青翠的箭羽", "翠绿猎人的笃定", "翠绿猎人的容器", "翠绿的猎人之冠",
-        "乐团的晨光", "琴师的箭羽", "终幕的时计", "终末的时计", "吟游者之壶", "指挥的礼帽",
-        "战狂的蔷薇", "战狂的翎羽", "战狂的时计", "战狂的骨杯", "战狂的鬼面",
-        "勇士的勋章", "勇士的期许", "勇士的坚毅", "勇士的壮行", "勇士的冠冕",
-        "守护之花", "守护徽印", "守护座钟", "守护之皿", "守护束带",
-        "流放者之花", "流放者之羽", "流放者怀表", "流放者之杯", "流放者头冠",
-        "赌徒的胸花", "赌徒的羽饰", "赌徒的怀表", "赌徒的骰盅", "赌徒的耳环",
-        "教官的胸花", "教官的羽饰", "教官的怀表", "教官的茶杯", "教官的帽子",
-        "武人的红花", "武人的羽饰", "武人的水漏", "武人的酒杯", "武人的头巾",
-        "祭水礼冠", "祭火礼冠", "祭雷礼冠", "祭冰礼冠",
-        "故人之心", "归乡之羽", "逐光之石", "异国之盏", "感别之冠",
-        "学士的书签", "学士的羽笔", "学士的时钟", "学士的墨杯", "学士的镜片",
-        "奇迹之花", "奇迹之羽", "奇迹之沙", "奇迹之杯", "奇迹耳坠",
-        "冒险家之花", "冒险家尾羽", "冒险家怀表", "冒险家金杯", "冒险家头带",
-        "幸运儿绿花", "幸运儿鹰羽", "幸运儿沙漏", "幸运儿之杯", "幸运儿银冠",
-        "游医的银莲", "游医的枭羽", "游医的怀钟", "游医的药壶", "游医的方巾",
-        "勋绩之花", "昭武翎羽", "金铜时晷", "盟誓金爵", "将帅兜鍪",
-        "无垢之花", "贤医之羽", "停摆之刻", "超越之盏", "嗤笑之面",
-        "明威之镡", "切落之羽", "雷云之笼", "绯花之壶", "华饰之兜",
-        "羁缠之花", "思忆之矢", "朝露之时", "祈望之心", "无常之面",
-    ];
+    let all_artifact_chs = ALL_ARTIFACT_CHS;
 
     let mut min_index = 0;
     let mut min_dis = edit_distance::edit_distance(raw, all_artifact_chs[0]);
@@ -229,11 +349,10 @@ impl ArtifactSetName {
         //     None => return None,
         // };
         // println!("name: {}", s);
-        match s {
+        let s = normalize_homoglyph(s);
+        match s.as_str() {
             "磐陀裂生之花" => Some(ArtifactSetName::ArchaicPetra),
             "嵯峨群峰之翼" => Some(ArtifactSetName::ArchaicPetra),
-            "星罗圭壁之晷" => Some(ArtifactSetName::ArchaicPetra),
-            // "壁" is different
             "星罗圭璧之晷" => Some(ArtifactSetName::ArchaicPetra),
             "巉岩琢塑之樽" => Some(ArtifactSetName::ArchaicPetra),
             "不动玄石之相" => Some(ArtifactSetName::ArchaicPetra),
@@ -395,18 +514,266 @@ impl ArtifactSetName {
             _ => None,
         }
     }
+    pub fn all() -> &'static [ArtifactSetName] {
+        &[
+            ArtifactSetName::ArchaicPetra,
+            ArtifactSetName::HeartOfDepth,
+            ArtifactSetName::BlizzardStrayer,
+            ArtifactSetName::RetracingBolide,
+            ArtifactSetName::NoblesseOblige,
+            ArtifactSetName::GladiatorFinale,
+            ArtifactSetName::MaidenBeloved,
+            ArtifactSetName::ViridescentVenerer,
+            ArtifactSetName::LavaWalker,
+            ArtifactSetName::CrimsonWitch,
+            ArtifactSetName::ThunderSmoother,
+            ArtifactSetName::ThunderingFury,
+            ArtifactSetName::BloodstainedChivalry,
+            ArtifactSetName::WandererTroupe,
+            ArtifactSetName::Scholar,
+            ArtifactSetName::Gambler,
+            ArtifactSetName::TinyMiracle,
+            ArtifactSetName::MartialArtist,
+            ArtifactSetName::BraveHeart,
+            ArtifactSetName::ResolutionOfSojourner,
+            ArtifactSetName::DefenderWill,
+            ArtifactSetName::Berserker,
+            ArtifactSetName::Instructor,
+            ArtifactSetName::Exile,
+            ArtifactSetName::Adventurer,
+            ArtifactSetName::LuckyDog,
+            ArtifactSetName::TravelingDoctor,
+            ArtifactSetName::PrayersForWisdom,
+            ArtifactSetName::PrayersToSpringtime,
+            ArtifactSetName::PrayersForIllumination,
+            ArtifactSetName::PrayersForDestiny,
+            ArtifactSetName::PaleFlame,
+            ArtifactSetName::TenacityOfTheMillelith,
+            ArtifactSetName::EmblemOfSeveredFate,
+            ArtifactSetName::ShimenawaReminiscence,
+        ]
+    }
+
+    // the set name, not the name of a piece
+    pub fn to_zh_cn(&self) -> String {
+        let temp = match self {
+            ArtifactSetName::ArchaicPetra => "悠古的磐岩",
+            ArtifactSetName::HeartOfDepth => "沉沦之心",
+            ArtifactSetName::BlizzardStrayer => "冰风迷途的勇士",
+            ArtifactSetName::RetracingBolide => "逆飞的流星",
+            ArtifactSetName::NoblesseOblige => "昔日宗室之仪",
+            ArtifactSetName::GladiatorFinale => "角斗士的终幕礼",
+            ArtifactSetName::MaidenBeloved => "被怜爱的少女",
+            ArtifactSetName::ViridescentVenerer => "翠绿之影",
+            ArtifactSetName::LavaWalker => "渡过烈火的贤人",
+            ArtifactSetName::CrimsonWitch => "炽烈的炎之魔女",
+            ArtifactSetName::ThunderSmoother => "平息鸣雷的尊者",
+            ArtifactSetName::ThunderingFury => "如雷的盛怒",
+            ArtifactSetName::BloodstainedChivalry => "染血的骑士道",
+            ArtifactSetName::WandererTroupe => "流浪大地的乐团",
+            ArtifactSetName::Scholar => "学士",
+            ArtifactSetName::Gambler => "赌徒",
+            ArtifactSetName::TinyMiracle => "奇迹",
+            ArtifactSetName::MartialArtist => "武人",
+            ArtifactSetName::BraveHeart => "勇士之心",
+            ArtifactSetName::ResolutionOfSojourner => "行者之心",
+            ArtifactSetName::DefenderWill => "守护之心",
+            ArtifactSetName::Berserker => "战狂",
+            ArtifactSetName::Instructor => "教官",
+            ArtifactSetName::Exile => "流放者",
+            ArtifactSetName::Adventurer => "冒险家",
+            ArtifactSetName::LuckyDog => "幸运儿",
+            ArtifactSetName::TravelingDoctor => "游医",
+            ArtifactSetName::PrayersForWisdom => "祭雷之人",
+            ArtifactSetName::PrayersToSpringtime => "祭冰之人",
+            ArtifactSetName::PrayersForIllumination => "祭火之人",
+            ArtifactSetName::PrayersForDestiny => "祭水之人",
+            ArtifactSetName::PaleFlame => "苍白之火",
+            ArtifactSetName::TenacityOfTheMillelith => "千岩牢固",
+            ArtifactSetName::EmblemOfSeveredFate => "绝缘之旗印",
+            ArtifactSetName::ShimenawaReminiscence => "追忆之注连",
+        };
+        String::from(temp)
+    }
+
+    // accepts either the enum name (e.g. "CrimsonWitch") or the zh_cn set name
+    pub fn from_name(s: &str) -> Option<ArtifactSetName> {
+        for set in ArtifactSetName::all().iter() {
+            if set.to_string() == s || set.to_zh_cn() == s {
+                return Some(set.clone());
+            }
+        }
+
+        None
+    }
+
+    // a match rather than a map, so a new set does not compile until its bonus is added
+    pub fn bonus_info(&self) -> SetBonus {
+        match self {
+            ArtifactSetName::ArchaicPetra => SetBonus {
+                effects: &[(2, "岩元素伤害加成提高15%"), (4, "获得元素晶片时，队伍中所有角色获得相应元素伤害加成提高35%，持续10秒")],
+                stats: &[ArtifactStatName::GeoBonus],
+            },
+            ArtifactSetName::HeartOfDepth => SetBonus {
+                effects: &[(2, "水元素伤害加成提高15%"), (4, "施放元素战技后的15秒内，普通攻击与重击造成的伤害提高30%")],
+                stats: &[ArtifactStatName::HydroBonus, ArtifactStatName::AtkPercentage],
+            },
+            ArtifactSetName::BlizzardStrayer => SetBonus {
+                effects: &[(2, "冰元素伤害加成提高15%"), (4, "攻击处于冰元素影响下的敌人时，暴击率提高20%；若敌人处于冻结状态，则暴击率额外提高20%")],
+                stats: &[ArtifactStatName::CryoBonus, ArtifactStatName::CriticalDamage],
+            },
+            ArtifactSetName::RetracingBolide => SetBonus {
+                effects: &[(2, "护盾强效提高35%"), (4, "处于护盾庇护下时，额外获得40%普通攻击和重击伤害加成")],
+                stats: &[ArtifactStatName::AtkPercentage],
+            },
+            ArtifactSetName::NoblesseOblige => SetBonus {
+                effects: &[(2, "元素爆发造成的伤害提升20%"), (4, "施放元素爆发后，队伍中所有角色攻击力提升20%，持续12秒，该效果不可叠加")],
+                stats: &[ArtifactStatName::Recharge, ArtifactStatName::AtkPercentage],
+            },
+            ArtifactSetName::GladiatorFinale => SetBonus {
+                effects: &[(2, "攻击力提高18%"), (4, "装备者为单手剑、双手剑、长柄武器角色时，普通攻击造成的伤害提高35%")],
+                stats: &[ArtifactStatName::AtkPercentage],
+            },
+            ArtifactSetName::MaidenBeloved => SetBonus {
+                effects: &[(2, "角色造成的治疗效果提升15%"), (4, "施放元素战技或元素爆发后的10秒内，队伍中所有角色受治疗效果加成提高20%")],
+                stats: &[ArtifactStatName::HealingBonus, ArtifactStatName::HpPercentage],
+            },
+            ArtifactSetName::ViridescentVenerer => SetBonus {
+                effects: &[(2, "风元素伤害加成提高15%"), (4, "扩散反应造成的伤害提升60%，并降低敌人对被扩散元素的抗性40%，持续10秒")],
+                stats: &[ArtifactStatName::AnemoBonus, ArtifactStatName::ElementalMastery],
+            },
+            ArtifactSetName::LavaWalker => SetBonus {
+                effects: &[(2, "火元素抗性提高40%"), (4, "对处于燃烧状态或火元素影响下的敌人，造成的伤害提高35%")],
+                stats: &[ArtifactStatName::AtkPercentage],
+            },
+            ArtifactSetName::CrimsonWitch => SetBonus {
+                effects: &[(2, "火元素伤害加成提高15%"), (4, "超载、燃烧反应造成的伤害提升40%，蒸发、融化反应的加成系数提高15%。施放元素战技后的10秒内，2件套的效果提高50%，最多叠加3次")],
+                stats: &[ArtifactStatName::PyroBonus, ArtifactStatName::ElementalMastery],
+            },
+            ArtifactSetName::ThunderSmoother => SetBonus {
+                effects: &[(2, "雷元素抗性提高40%"), (4, "对处于雷元素影响下的敌人，造成的伤害提高35%")],
+                stats: &[ArtifactStatName::AtkPercentage],
+            },
+            ArtifactSetName::ThunderingFury => SetBonus {
+                effects: &[(2, "雷元素伤害加成提高15%"), (4, "超载、感电、超导反应造成的伤害提升40%。触发这些反应时，元素战技冷却时间减少1秒，每0.8秒最多触发一次")],
+                stats: &[ArtifactStatName::ElectroBonus, ArtifactStatName::ElementalMastery],
+            },
+            ArtifactSetName::BloodstainedChivalry => SetBonus {
+                effects: &[(2, "造成的物理伤害提高25%"), (4, "击败敌人后的10秒内，施放重击时不消耗体力，且重击造成的伤害提升50%")],
+                stats: &[ArtifactStatName::PhysicalBonus],
+            },
+            ArtifactSetName::WandererTroupe => SetBonus {
+                effects: &[(2, "元素精通提高80点"), (4, "装备者为法器、弓箭角色时，重击造成的伤害提高35%")],
+                stats: &[ArtifactStatName::ElementalMastery],
+            },
+            ArtifactSetName::Scholar => SetBonus {
+                effects: &[(2, "元素充能效率提高20%"), (4, "获得元素微粒或元素晶球时，队伍中所有弓箭和法器角色额外恢复3点元素能量，每3秒只能触发一次")],
+                stats: &[ArtifactStatName::Recharge],
+            },
+            ArtifactSetName::Gambler => SetBonus {
+                effects: &[(2, "元素战技造成的伤害提升20%"), (4, "击败敌人时，有100%概率清除元素战技的冷却时间，每15秒只能触发一次")],
+                stats: &[ArtifactStatName::AtkPercentage],
+            },
+            ArtifactSetName::TinyMiracle => SetBonus {
+                effects: &[(2, "所有元素抗性提高20%"), (4, "受到某个元素类型的伤害后，相应的抗性提升30%，持续10秒，每10秒只能触发一次")],
+                stats: &[],
+            },
+            ArtifactSetName::MartialArtist => SetBonus {
+                effects: &[(2, "普通攻击与重击造成的伤害提高15%"), (4, "施放元素战技后的8秒内，普通攻击和重击造成的伤害提升25%")],
+                stats: &[ArtifactStatName::AtkPercentage],
+            },
+            ArtifactSetName::BraveHeart => SetBonus {
+                effects: &[(2, "攻击力提高18%"), (4, "对生命值高于50%的敌人，造成的伤害增加30%")],
+                stats: &[ArtifactStatName::AtkPercentage],
+            },
+            ArtifactSetName::ResolutionOfSojourner => SetBonus {
+                effects: &[(2, "攻击力提高18%"), (4, "重击的暴击率提升30%")],
+                stats: &[ArtifactStatName::AtkPercentage, ArtifactStatName::Critical],
+            },
+            ArtifactSetName::DefenderWill => SetBonus {
+                effects: &[(2, "防御力提高30%"), (4, "队伍里每有一种元素类型的角色，自身对应的元素抗性提升30%")],
+                stats: &[ArtifactStatName::DefPercentage],
+            },
+            ArtifactSetName::Berserker => SetBonus {
+                effects: &[(2, "暴击率提高12%"), (4, "生命值低于70%时，暴击率提升24%")],
+                stats: &[ArtifactStatName::Critical],
+            },
+            ArtifactSetName::Instructor => SetBonus {
+                effects: &[(2, "元素精通提高80点"), (4, "触发元素反应后，队伍中所有角色的元素精通提高120点，持续8秒")],
+                stats: &[ArtifactStatName::ElementalMastery],
+            },
+            ArtifactSetName::Exile => SetBonus {
+                effects: &[(2, "元素充能效率提高20%"), (4, "施放元素爆发后，每2秒为队伍中所有角色（不包括自己）恢复2点元素能量，持续6秒，该效果无法叠加")],
+                stats: &[ArtifactStatName::Recharge],
+            },
+            ArtifactSetName::Adventurer => SetBonus {
+                effects: &[(2, "生命值上限提高1000点"), (4, "打开宝箱后的5秒内，持续恢复30%生命值")],
+                stats: &[ArtifactStatName::Hp],
+            },
+            ArtifactSetName::LuckyDog => SetBonus {
+                effects: &[(2, "防御力提高100点"), (4, "拾取摩拉时，恢复300点生命值")],
+                stats: &[ArtifactStatName::Def],
+            },
+            ArtifactSetName::TravelingDoctor => SetBonus {
+                effects: &[(2, "受治疗效果提升20%"), (4, "施放元素爆发时，恢复20%生命值")],
+                stats: &[],
+            },
+            ArtifactSetName::PrayersForWisdom => SetBonus {
+                effects: &[(1, "受到雷元素附着影响的持续时间缩短40%")],
+                stats: &[],
+            },
+            ArtifactSetName::PrayersToSpringtime => SetBonus {
+                effects: &[(1, "受到冰元素附着影响的持续时间缩短40%")],
+                stats: &[],
+            },
+            ArtifactSetName::PrayersForIllumination => SetBonus {
+                effects: &[(1, "受到火元素附着影响的持续时间缩短40%")],
+                stats: &[],
+            },
+            ArtifactSetName::PrayersForDestiny => SetBonus {
+                effects: &[(1, "受到水元素附着影响的持续时间缩短40%")],
+                stats: &[],
+            },
+            ArtifactSetName::PaleFlame => SetBonus {
+                effects: &[(2, "造成的物理伤害提高25%"), (4, "元素战技命中敌人后，攻击力提升9%，持续7秒，至多叠加2层，每0.3秒至多触发一次。叠加至2层时，2件套的效果提高100%")],
+                stats: &[ArtifactStatName::PhysicalBonus, ArtifactStatName::AtkPercentage],
+            },
+            ArtifactSetName::TenacityOfTheMillelith => SetBonus {
+                effects: &[(2, "生命值提升20%"), (4, "元素战技命中敌人后，队伍中附近所有角色攻击力提升20%，护盾强效提升30%，持续3秒，每0.5秒至多触发一次，装备者处于后台时依然能触发")],
+                stats: &[ArtifactStatName::HpPercentage],
+            },
+            ArtifactSetName::EmblemOfSeveredFate => SetBonus {
+                effects: &[(2, "元素充能效率提高20%"), (4, "基于元素充能效率的25%，提高元素爆发造成的伤害，至多通过这种方式获得75%提升")],
+                stats: &[ArtifactStatName::Recharge],
+            },
+            ArtifactSetName::ShimenawaReminiscence => SetBonus {
+                effects: &[(2, "攻击力提高18%"), (4, "施放元素战技时，如果元素能量不低于15点，则流失15点元素能量，使接下来的10秒内普通攻击、重击、下落攻击造成的伤害提高50%，持续期间内不会再次触发")],
+                stats: &[ArtifactStatName::AtkPercentage],
+            },
+        }
+    }
 }
 
 impl ArtifactSlot {
+    pub fn all() -> &'static [ArtifactSlot] {
+        &[
+            ArtifactSlot::Flower,
+            ArtifactSlot::Feather,
+            ArtifactSlot::Sand,
+            ArtifactSlot::Goblet,
+            ArtifactSlot::Head,
+        ]
+    }
+
     pub fn from_zh_cn(s: &str) -> Option<ArtifactSlot> {
         // let s = match get_real_artifact_name_chs(s) {
         //     Some(v) => v,
         //     None => return None,
         // };
-        match s {
+        let s = normalize_homoglyph(s);
+        match s.as_str() {
             "磐陀裂生之花" => Some(ArtifactSlot::Flower),
             "嵯峨群峰之翼" => Some(ArtifactSlot::Feather),
-            "星罗圭壁之晷" => Some(ArtifactSlot::Sand),
             "星罗圭璧之晷" => Some(ArtifactSlot::Sand),
             "巉岩琢塑之樽" => Some(ArtifactSlot::Goblet),
             "不动玄石之相" => Some(ArtifactSlot::Head),
@@ -570,3 +937,176 @@ impl ArtifactSlot {
     }
 }
 
+// Display and FromStr use the variant name, e.g. "CriticalDamage"
+impl fmt::Display for ArtifactStatName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for ArtifactStatName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ArtifactStatName, String> {
+        for x in ArtifactStatName::all().iter() {
+            if x.to_string() == s {
+                return Ok(x.clone());
+            }
+        }
+
+        Err(format!("unknown ArtifactStatName: {}", s))
+    }
+}
+
+impl fmt::Display for ArtifactSlot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for ArtifactSlot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ArtifactSlot, String> {
+        for x in ArtifactSlot::all().iter() {
+            if x.to_string() == s {
+                return Ok(x.clone());
+            }
+        }
+
+        Err(format!("unknown ArtifactSlot: {}", s))
+    }
+}
+
+impl fmt::Display for ArtifactSetName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for ArtifactSetName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ArtifactSetName, String> {
+        for x in ArtifactSetName::all().iter() {
+            if x.to_string() == s {
+                return Ok(x.clone());
+            }
+        }
+
+        Err(format!("unknown ArtifactSetName: {}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeSet, HashSet};
+
+    // the keys of a from_zh_cn table, read from the match arms in this file,
+    // e.g. `"宗室之花" => Some(ArtifactSlot::Flower),`
+    fn table_keys(target: &str) -> BTreeSet<String> {
+        let arm = format!("=> Some({}::", target);
+        include_str!("internal_artifact.rs").lines()
+            .map(|line| line.trim())
+            .filter(|line| line.starts_with('"') && line.contains(&arm))
+            .map(|line| line[1..].split('"').next().unwrap().to_string())
+            .collect()
+    }
+
+    fn assert_same(name_a: &str, a: &BTreeSet<String>, name_b: &str, b: &BTreeSet<String>) {
+        let only_a: Vec<&String> = a.difference(b).collect();
+        let only_b: Vec<&String> = b.difference(a).collect();
+        assert!(
+            only_a.is_empty() && only_b.is_empty(),
+            "only in {}: {:?}, only in {}: {:?}", name_a, only_a, name_b, only_b
+        );
+    }
+
+    #[test]
+    fn test_name_tables_match() {
+        let set_keys = table_keys("ArtifactSetName");
+        let slot_keys = table_keys("ArtifactSlot");
+        let all: BTreeSet<String> = ALL_ARTIFACT_CHS.iter().map(|x| x.to_string()).collect();
+        assert!(!set_keys.is_empty());
+
+        assert_same("ArtifactSetName::from_zh_cn", &set_keys, "ArtifactSlot::from_zh_cn", &slot_keys);
+        assert_same("ArtifactSetName::from_zh_cn", &set_keys, "ALL_ARTIFACT_CHS", &all);
+        assert_same("ArtifactSlot::from_zh_cn", &slot_keys, "ALL_ARTIFACT_CHS", &all);
+    }
+
+    #[test]
+    fn test_name_tables_resolve() {
+        for &name in ALL_ARTIFACT_CHS.iter() {
+            assert!(ArtifactSetName::from_zh_cn(name).is_some(), "no set for {}", name);
+            assert!(ArtifactSlot::from_zh_cn(name).is_some(), "no slot for {}", name);
+        }
+    }
+
+    #[test]
+    fn test_display_from_str_round_trip() {
+        for x in ArtifactStatName::all().iter() {
+            assert_eq!(x.to_string().parse::<ArtifactStatName>(), Ok(x.clone()));
+        }
+        for x in ArtifactSlot::all().iter() {
+            assert_eq!(x.to_string().parse::<ArtifactSlot>(), Ok(x.clone()));
+        }
+        for x in ArtifactSetName::all().iter() {
+            assert_eq!(x.to_string().parse::<ArtifactSetName>(), Ok(x.clone()));
+        }
+        assert_eq!("CriticalDamage".parse::<ArtifactStatName>(), Ok(ArtifactStatName::CriticalDamage));
+        assert!("criticaldamage".parse::<ArtifactStatName>().is_err());
+    }
+
+    #[test]
+    fn test_normalize_homoglyph() {
+        assert_eq!(normalize_homoglyph("星罗圭壁之晷"), "星罗圭璧之晷");
+        assert_eq!(normalize_homoglyph("宗室之花"), "宗室之花");
+        assert_eq!(normalize_homoglyph(""), "");
+    }
+
+    #[test]
+    fn test_from_zh_cn_confused_input() {
+        // read by the model with 壁, only the homoglyph table maps it to the 璧 entries
+        let confused = "星罗圭壁之晷";
+        assert!(!table_keys("ArtifactSetName").contains(confused));
+        assert!(!table_keys("ArtifactSlot").contains(confused));
+        assert_eq!(ArtifactSetName::from_zh_cn(confused), Some(ArtifactSetName::ArchaicPetra));
+        assert_eq!(ArtifactSlot::from_zh_cn(confused), Some(ArtifactSlot::Sand));
+    }
+
+    #[test]
+    fn test_bonus_info_complete() {
+        // every set that can be recognized must be listed in all()
+        for &name in ALL_ARTIFACT_CHS.iter() {
+            let set = ArtifactSetName::from_zh_cn(name).unwrap();
+            assert!(ArtifactSetName::all().contains(&set), "{:?} missing from all()", set);
+        }
+
+        for set in ArtifactSetName::all().iter() {
+            let bonus = set.bonus_info();
+            assert!(!bonus.effects.is_empty(), "{:?} has no effects", set);
+            let mut last_pieces = 0;
+            for &(pieces, text) in bonus.effects.iter() {
+                assert!(pieces == 1 || pieces == 2 || pieces == 4, "{:?} has a {} piece effect", set, pieces);
+                assert!(pieces > last_pieces, "{:?} effects are not in piece order", set);
+                assert!(!text.is_empty(), "{:?} has an empty {} piece effect", set, pieces);
+                last_pieces = pieces;
+            }
+            let stats: HashSet<&ArtifactStatName> = bonus.stats.iter().collect();
+            assert_eq!(stats.len(), bonus.stats.len(), "{:?} lists a stat twice", set);
+        }
+    }
+
+    #[test]
+    fn test_expected_sub_stat_count() {
+        assert_eq!(expected_sub_stat_count(5, 0), (3, 4));
+        assert_eq!(expected_sub_stat_count(5, 4), (4, 4));
+        assert_eq!(expected_sub_stat_count(5, 20), (4, 4));
+        assert_eq!(expected_sub_stat_count(4, 0), (2, 3));
+        assert_eq!(expected_sub_stat_count(4, 7), (3, 4));
+        assert_eq!(expected_sub_stat_count(3, 0), (1, 2));
+        assert_eq!(expected_sub_stat_count(1, 0), (0, 0));
+        assert_eq!(expected_sub_stat_count(1, 4), (1, 1));
+    }
+}
\ No newline at end of file
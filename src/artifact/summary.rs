@@ -0,0 +1,27 @@
+use std::collections::BTreeMap;
+
+use log::info;
+
+use crate::artifact::internal_artifact::{ArtifactSlot, ArtifactStatName, InternalArtifact};
+
+// how often each main stat occurs, per slot. stats are ordered as in
+// ArtifactStatName, which keeps the flat and percentage variants together
+pub fn main_stat_distribution(results: &[InternalArtifact]) -> BTreeMap<ArtifactSlot, BTreeMap<ArtifactStatName, u32>> {
+    let mut dist: BTreeMap<ArtifactSlot, BTreeMap<ArtifactStatName, u32>> = BTreeMap::new();
+    for art in results.iter() {
+        let slot = dist.entry(art.slot.clone()).or_default();
+        *slot.entry(art.main_stat.name.clone()).or_insert(0) += 1;
+    }
+
+    dist
+}
+
+pub fn log_main_stat_distribution(results: &[InternalArtifact]) {
+    for (slot, stats) in main_stat_distribution(results).iter() {
+        let total: u32 = stats.values().sum();
+        info!("{} main stats ({}):", slot, total);
+        for (name, count) in stats.iter() {
+            info!("    {}: {} ({:.1}%)", name, count, *count as f64 * 100.0 / total as f64);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+
+use crate::capture::{Capturer, image_to_raw};
+use crate::common::PixelRect;
+use crate::common::color::Color;
+
+type Frames = HashMap<(i32, i32, i32, i32), VecDeque<Vec<u8>>>;
+
+// serves prepared captures instead of the screen, so that the scanner can be
+// driven without the game. frames are keyed by the exact rect requested and
+// returned in order, the last one of a rect repeats. rects without frames
+// capture as black
+pub struct MockCapturer {
+    frames: RefCell<Frames>,
+    count: Cell<u32>,
+}
+
+fn key(rect: &PixelRect) -> (i32, i32, i32, i32) {
+    (rect.left, rect.top, rect.width, rect.height)
+}
+
+impl MockCapturer {
+    pub fn new() -> MockCapturer {
+        MockCapturer {
+            frames: RefCell::new(HashMap::new()),
+            count: Cell::new(0),
+        }
+    }
+
+    // data is BGRA from the bottom row up, like a screen capture
+    pub fn push_frame(&self, rect: &PixelRect, data: Vec<u8>) {
+        self.frames.borrow_mut().entry(key(rect)).or_default().push_back(data);
+    }
+
+    pub fn push_image(&self, rect: &PixelRect, img: &image::RgbImage) {
+        self.push_frame(rect, image_to_raw(img));
+    }
+
+    // e.g. a png saved by --capture-only or --debug-regions
+    pub fn push_file(&self, rect: &PixelRect, path: &str) -> Result<(), String> {
+        let img = image::open(path).map_err(|e| format!("cannot open {}: {}", path, e))?.to_rgb8();
+        if img.width() != rect.width as u32 || img.height() != rect.height as u32 {
+            return Err(format!("{} is {}x{}, expected {}x{}", path, img.width(), img.height(), rect.width, rect.height));
+        }
+        self.push_image(rect, &img);
+
+        Ok(())
+    }
+
+    pub fn push_color(&self, x: u32, y: u32, color: &Color) {
+        let rect = PixelRect {
+            left: x as i32,
+            top: y as i32,
+            width: 1,
+            height: 1,
+        };
+        self.push_frame(&rect, vec![color.2, color.1, color.0, 255]);
+    }
+
+    // captures served so far
+    pub fn count(&self) -> u32 {
+        self.count.get()
+    }
+}
+
+impl Default for MockCapturer {
+    fn default() -> MockCapturer {
+        MockCapturer::new()
+    }
+}
+
+impl Capturer for MockCapturer {
+    fn capture_absolute(&self, rect: &PixelRect) -> Result<Vec<u8>, String> {
+        self.count.set(self.count.get() + 1);
+        let mut frames = self.frames.borrow_mut();
+        let data = match frames.get_mut(&key(rect)) {
+            Some(queue) if queue.len() > 1 => queue.pop_front().unwrap(),
+            Some(queue) => queue[0].clone(),
+            None => vec![0; (rect.width * rect.height * 4) as usize],
+        };
+
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_in_order_last_repeats() {
+        let capturer = MockCapturer::new();
+        let rect = PixelRect { left: 1, top: 2, width: 2, height: 1 };
+        capturer.push_frame(&rect, vec![1; 8]);
+        capturer.push_frame(&rect, vec![2; 8]);
+
+        assert_eq!(capturer.capture_absolute(&rect), Ok(vec![1; 8]));
+        assert_eq!(capturer.capture_absolute(&rect), Ok(vec![2; 8]));
+        assert_eq!(capturer.capture_absolute(&rect), Ok(vec![2; 8]));
+        let other = PixelRect { left: 0, top: 0, width: 2, height: 1 };
+        assert_eq!(capturer.capture_absolute(&other), Ok(vec![0; 8]));
+        assert_eq!(capturer.count(), 4);
+    }
+
+    #[test]
+    fn test_image_round_trip() {
+        let mut img = image::RgbImage::new(2, 2);
+        img.put_pixel(0, 0, image::Rgb([10, 20, 30]));
+        img.put_pixel(1, 1, image::Rgb([40, 50, 60]));
+        let rect = PixelRect { left: 0, top: 0, width: 2, height: 2 };
+        let capturer = MockCapturer::new();
+        capturer.push_image(&rect, &img);
+
+        assert_eq!(capturer.capture_absolute_image(&rect).unwrap(), img);
+        assert_eq!(capturer.get_color(0, 0).unwrap().0, 0);
+        capturer.push_color(5, 5, &Color(1, 2, 3));
+        let color = capturer.get_color(5, 5).unwrap();
+        assert_eq!((color.0, color.1, color.2), (1, 2, 3));
+    }
+}
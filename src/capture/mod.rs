@@ -40,6 +40,7 @@ use crate::common::{PixelRect, PixelRectBound};
 use crate::common::color::Color;
 use winapi::shared::windef::DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE;
 
+pub mod mock;
 
 #[cfg(windows)]
 unsafe fn unsafe_capture(rect: &PixelRect) -> Result<Vec<u8>, String> {
@@ -165,10 +166,27 @@ pub fn capture_absolute_image(rect: &PixelRect) -> Result<image::RgbImage, Strin
         Ok(v) => v,
     };
 
-    let height = rect.height as u32;
-    let width = rect.width as u32;
+    Ok(raw_to_image(&raw, rect.width as u32, rect.height as u32))
+}
+
+#[cfg(windows)]
+pub fn get_color(x: u32, y: u32) -> Result<Color, String> {
+    let im = capture_absolute(&PixelRect {
+        left: x as i32,
+        top: y as i32,
+        width: 1,
+        height: 1,
+    })?;
+
+    let b = im[0];
+    let g = im[1];
+    let r = im[2];
+    Ok(Color(r, g, b))
+}
 
-    let mut img = ImageBuffer::from_fn(
+// captures are BGRA with the rows from bottom to top
+pub fn raw_to_image(raw: &[u8], width: u32, height: u32) -> image::RgbImage {
+    ImageBuffer::from_fn(
         width,
         height,
         move |x, y| {
@@ -178,22 +196,49 @@ pub fn capture_absolute_image(rect: &PixelRect) -> Result<image::RgbImage, Strin
             let r = raw[((y * width + x) * 4 + 2) as usize];
             image::Rgb([r, g, b])
         }
-    );
+    )
+}
+
+pub fn image_to_raw(img: &image::RgbImage) -> Vec<u8> {
+    let (width, height) = img.dimensions();
+    let mut raw: Vec<u8> = vec![0; (width * height * 4) as usize];
+    for (x, y, p) in img.enumerate_pixels() {
+        let index = (((height - y - 1) * width + x) * 4) as usize;
+        raw[index] = p[2];
+        raw[index + 1] = p[1];
+        raw[index + 2] = p[0];
+    }
 
-    Ok(img)
+    raw
 }
 
-#[cfg(windows)]
-pub fn get_color(x: u32, y: u32) -> Color {
-    let im = capture_absolute(&PixelRect {
-        left: x as i32,
-        top: y as i32,
-        width: 1,
-        height: 1,
-    }).unwrap();
+// everything the scanner reads from the screen goes through this, so that
+// it can also run on recorded captures, see mock::MockCapturer
+pub trait Capturer {
+    fn capture_absolute(&self, rect: &PixelRect) -> Result<Vec<u8>, String>;
 
-    let b = im[0];
-    let g = im[1];
-    let r = im[2];
-    Color(r, g, b)
+    fn capture_absolute_image(&self, rect: &PixelRect) -> Result<image::RgbImage, String> {
+        let raw = self.capture_absolute(rect)?;
+        Ok(raw_to_image(&raw, rect.width as u32, rect.height as u32))
+    }
+
+    fn get_color(&self, x: u32, y: u32) -> Result<Color, String> {
+        let im = self.capture_absolute(&PixelRect {
+            left: x as i32,
+            top: y as i32,
+            width: 1,
+            height: 1,
+        })?;
+
+        Ok(Color(im[2], im[1], im[0]))
+    }
+}
+
+pub struct ScreenCapturer;
+
+#[cfg(windows)]
+impl Capturer for ScreenCapturer {
+    fn capture_absolute(&self, rect: &PixelRect) -> Result<Vec<u8>, String> {
+        capture_absolute(rect)
+    }
 }
\ No newline at end of file
@@ -1,6 +1,36 @@
+use serde::Deserialize;
+
 #[derive(Debug)]
 pub struct Color (pub u8, pub u8, pub u8);
 
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMetric {
+    // squared distance in rgb
+    Euclidean,
+    // squared distance weighted by the mean red value, closer to how
+    // different two colors look, e.g. on washed-out streams
+    RedMean,
+}
+
+impl ColorMetric {
+    pub fn from_name(s: &str) -> Option<ColorMetric> {
+        match s {
+            "euclidean" => Some(ColorMetric::Euclidean),
+            "redmean" => Some(ColorMetric::RedMean),
+            _ => None,
+        }
+    }
+
+    // below which two colors are the same, about the same strictness for both metrics
+    pub fn default_threshold(&self) -> u32 {
+        match self {
+            ColorMetric::Euclidean => 20,
+            ColorMetric::RedMean => 60,
+        }
+    }
+}
+
 impl Color {
     pub fn is_same(&self, other: &Color) -> bool {
         let dis = self.dis_2(other);
@@ -9,11 +39,27 @@ impl Color {
     }
 
     pub fn dis_2(&self, other: &Color) -> u32 {
-        let dis = (self.0 as u32 - other.0 as u32) * (self.0 as u32 - other.0 as u32)
-            + (self.1 as u32 - other.1 as u32) * (self.1 as u32 - other.1 as u32)
-            + (self.2 as u32 - other.2 as u32) * (self.2 as u32 - other.2 as u32)
-            ;
-        dis
+        let dr = self.0 as i32 - other.0 as i32;
+        let dg = self.1 as i32 - other.1 as i32;
+        let db = self.2 as i32 - other.2 as i32;
+        (dr * dr + dg * dg + db * db) as u32
+    }
+
+    pub fn dis(&self, other: &Color, metric: ColorMetric) -> u32 {
+        match metric {
+            ColorMetric::Euclidean => self.dis_2(other),
+            ColorMetric::RedMean => {
+                let r_mean = (self.0 as i32 + other.0 as i32) / 2;
+                let dr = self.0 as i32 - other.0 as i32;
+                let dg = self.1 as i32 - other.1 as i32;
+                let db = self.2 as i32 - other.2 as i32;
+                (((512 + r_mean) * dr * dr) / 256 + 4 * dg * dg + ((767 - r_mean) * db * db) / 256) as u32
+            },
+        }
+    }
+
+    pub fn is_same_with(&self, other: &Color, metric: ColorMetric, threshold: u32) -> bool {
+        self.dis(other, metric) < threshold
     }
 
     pub fn new() -> Color {
@@ -23,4 +69,46 @@ impl Color {
     pub fn from(r: u8, g: u8, b: u8) -> Color {
         Color(r, g, b)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metric_selection() {
+        assert_eq!(ColorMetric::from_name("euclidean"), Some(ColorMetric::Euclidean));
+        assert_eq!(ColorMetric::from_name("redmean"), Some(ColorMetric::RedMean));
+        assert_eq!(ColorMetric::from_name("RedMean"), None);
+        assert_eq!(serde_json::from_str::<ColorMetric>("\"redmean\"").unwrap(), ColorMetric::RedMean);
+        assert_eq!(ColorMetric::Euclidean.default_threshold(), 20);
+        assert_eq!(ColorMetric::RedMean.default_threshold(), 60);
+    }
+
+    #[test]
+    fn test_dis() {
+        let black = Color(0, 0, 0);
+        assert_eq!(black.dis(&Color(0, 0, 0), ColorMetric::Euclidean), 0);
+        assert_eq!(black.dis(&Color(0, 0, 0), ColorMetric::RedMean), 0);
+
+        // the same step weighs differently per channel under redmean
+        assert_eq!(black.dis(&Color(0, 10, 0), ColorMetric::Euclidean), 100);
+        assert_eq!(black.dis(&Color(0, 0, 10), ColorMetric::Euclidean), 100);
+        assert_eq!(black.dis(&Color(0, 10, 0), ColorMetric::RedMean), 400);
+        assert_eq!(black.dis(&Color(0, 0, 10), ColorMetric::RedMean), 299);
+        assert_eq!(Color(100, 0, 0).dis(&Color(110, 0, 0), ColorMetric::RedMean), 241);
+
+        assert_eq!(Color(1, 2, 3).dis(&Color(4, 6, 8), ColorMetric::Euclidean), Color(1, 2, 3).dis_2(&Color(4, 6, 8)));
+    }
+
+    #[test]
+    fn test_is_same_with() {
+        let a = Color(100, 100, 100);
+        let b = Color(105, 100, 100);
+        assert!(!a.is_same_with(&b, ColorMetric::Euclidean, ColorMetric::Euclidean.default_threshold()));
+        // a looser threshold, e.g. for a washed-out stream
+        assert!(a.is_same_with(&b, ColorMetric::Euclidean, 30));
+        assert!(a.is_same_with(&Color(103, 100, 100), ColorMetric::RedMean, ColorMetric::RedMean.default_threshold()));
+        assert_eq!(a.is_same(&b), a.is_same_with(&b, ColorMetric::Euclidean, 20));
+    }
 }
\ No newline at end of file
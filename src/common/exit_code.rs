@@ -0,0 +1,17 @@
+// process exit codes, for scripts wrapping yas. see the README for the list
+pub const SUCCESS: i32 = 0;
+// invalid arguments, missing admin rights and other errors
+pub const ERROR: i32 = 1;
+pub const WINDOW_NOT_FOUND: i32 = 2;
+// the scan stopped early, e.g. --max-duration or a failed capture; partial results are exported
+pub const INTERRUPTED: i32 = 3;
+// a scroll did not reach the next row, some rows may be missing
+pub const SCROLL_FAILED: i32 = 4;
+// some artifacts could not be recognized and are missing from the export
+pub const PARSE_ERRORS: i32 = 5;
+// --strict found impossible artifacts, nothing is exported
+pub const AUDIT_FAILED: i32 = 6;
+// a debug mode such as --single or --calibrate failed
+pub const DIAGNOSTIC_FAILED: i32 = 7;
+// --start-row is past the last row, nothing is scanned
+pub const INVALID_START_ROW: i32 = 8;
\ No newline at end of file
@@ -0,0 +1,55 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+
+// writes log output to stderr and to a file, optionally rotating the file
+// to "<path>.1" once it grows past max_size bytes
+pub struct LogFileWriter {
+    path: String,
+    file: File,
+    written: u64,
+    max_size: Option<u64>,
+}
+
+impl LogFileWriter {
+    pub fn new(path: String, max_size: Option<u64>) -> io::Result<LogFileWriter> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+
+        Ok(LogFileWriter {
+            path,
+            file,
+            written,
+            max_size,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        fs::rename(&self.path, format!("{}.1", self.path))?;
+        self.file = File::create(&self.path)?;
+        self.written = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stderr().write_all(buf)?;
+
+        if let Some(max_size) = self.max_size {
+            if self.written > 0 && self.written + buf.len() as u64 > max_size {
+                self.rotate()?;
+            }
+        }
+        self.file.write_all(buf)?;
+        self.written += buf.len() as u64;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()?;
+        self.file.flush()
+    }
+}
@@ -2,13 +2,16 @@ use crate::capture;
 use crate::inference::pre_process::{pre_process, to_gray, raw_to_img};
 use crate::info::info::ScanInfo;
 use image::{GrayImage, RgbImage};
-use crate::capture::capture_absolute;
+use crate::capture::{capture_absolute, Capturer};
 use std::time::SystemTime;
 use log::{info};
+use color::Color;
 
 pub mod utils;
 pub mod buffer;
 pub mod color;
+pub mod log_file;
+pub mod exit_code;
 
 #[derive(Debug)]
 pub struct PixelRect {
@@ -45,13 +48,17 @@ impl PixelRectBound {
             width: w,
             height: h,
         };
-        let raw_u8 = capture::capture_absolute(&rect).unwrap();
+        let raw_u8 = capture::capture_absolute(&rect)?;
         let raw_gray = to_gray(raw_u8, w as u32, h as u32);
         let raw_after_pp = pre_process(raw_gray);
         Ok(raw_after_pp)
     }
 
     pub fn capture_relative(&self, info: &ScanInfo) -> Result<RawImage, String> {
+        self.capture_relative_with(info, &capture::ScreenCapturer)
+    }
+
+    pub fn capture_relative_with(&self, info: &ScanInfo, capturer: &dyn Capturer) -> Result<RawImage, String> {
         let w = self.right - self.left;
         let h = self.bottom - self.top;
         let rect = PixelRect {
@@ -61,7 +68,7 @@ impl PixelRectBound {
             height: h,
         };
         let now = SystemTime::now();
-        let raw_u8 = capture::capture_absolute(&rect).unwrap();
+        let raw_u8 = capturer.capture_absolute(&rect)?;
         info!("capture raw time: {}ms", now.elapsed().unwrap().as_millis());
         let raw_gray = to_gray(raw_u8, w as u32, h as u32);
         let raw_after_pp = pre_process(raw_gray);
@@ -102,6 +109,30 @@ impl RawImage {
 }
 
 impl RawCaptureImage {
+    // x and y are relative to the captured rect
+    pub fn get_color(&self, x: u32, y: u32) -> Color {
+        let y = self.h - y - 1;
+        let index = ((y * self.w + x) * 4) as usize;
+        Color::from(self.data[index + 2], self.data[index + 1], self.data[index])
+    }
+
+    // rect is relative to the captured rect, the result is BGRA from the
+    // bottom row up as well
+    pub fn crop(&self, rect: &PixelRect) -> RawCaptureImage {
+        let (w, h) = (rect.width as u32, rect.height as u32);
+        let mut data: Vec<u8> = Vec::with_capacity((w * h * 4) as usize);
+        for y in (rect.top as u32..rect.top as u32 + h).rev() {
+            let start = (((self.h - y - 1) * self.w + rect.left as u32) * 4) as usize;
+            data.extend_from_slice(&self.data[start..start + (w * 4) as usize]);
+        }
+
+        RawCaptureImage {
+            data,
+            w,
+            h,
+        }
+    }
+
     pub fn crop_and_preprocess(&self, rect: &PixelRect) -> RawImage {
         // let now = SystemTime::now();
         let vol = rect.width * rect.height;
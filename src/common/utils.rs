@@ -8,10 +8,10 @@ use std::io::stdin;
 use std::process;
 
 use log::{error, info};
-use winapi::um::winuser::{FindWindowW, GetClientRect, ClientToScreen};
+use winapi::um::winuser::{FindWindowW, GetClientRect, ClientToScreen, GetForegroundWindow, IsWindow};
 use winapi::shared::windef::{HWND, RECT as WinRect, POINT as WinPoint};
 
-use crate::common::PixelRect;
+use crate::common::{PixelRect, exit_code};
 use winapi::um::winnt::{SID_IDENTIFIER_AUTHORITY, SECURITY_NT_AUTHORITY, PSID, SECURITY_BUILTIN_DOMAIN_RID, DOMAIN_ALIAS_RID_ADMINS};
 use winapi::um::securitybaseapi::{AllocateAndInitializeSid, CheckTokenMembership, FreeSid};
 use winapi::shared::minwindef::BOOL;
@@ -58,12 +58,26 @@ unsafe fn get_client_rect_unsafe(hwnd: HWND) -> Result<PixelRect, String> {
     })
 }
 
-pub fn get_client_rect(hwnd: HWND) -> Result<PixelRect, String> {
+pub(crate) fn get_client_rect(hwnd: HWND) -> Result<PixelRect, String> {
     unsafe {
         get_client_rect_unsafe(hwnd)
     }
 }
 
+pub fn is_foreground(hwnd: HWND) -> bool {
+    let foreground = unsafe {
+        GetForegroundWindow()
+    };
+    foreground == hwnd
+}
+
+// false once the window is closed
+pub(crate) fn is_window(hwnd: HWND) -> bool {
+    unsafe {
+        IsWindow(hwnd) != 0
+    }
+}
+
 pub fn sleep(ms: u32) {
     let time = time::Duration::from_millis(ms as u64);
     thread::sleep(time);
@@ -75,10 +89,14 @@ pub fn read_file_to_string(path: String) -> String {
 }
 
 pub fn error_and_quit(msg: &str) -> ! {
+    error_and_quit_with_code(msg, exit_code::ERROR)
+}
+
+pub fn error_and_quit_with_code(msg: &str, code: i32) -> ! {
     error!("{}, 按Enter退出", msg);
     let mut s: String = String::new();
     stdin().read_line(&mut s);
-    process::exit(0);
+    process::exit(code);
 }
 
 unsafe fn is_admin_unsafe() -> bool {
@@ -1,8 +1,10 @@
 use std::convert::From;
 use std::fs::File;
 use std::io::prelude::*;
+use std::io;
 
 use serde::ser::{Serialize, Serializer, SerializeMap};
+use serde_json::Value;
 
 use crate::artifact::internal_artifact::{ArtifactStatName, ArtifactSetName, ArtifactSlot, InternalArtifact, ArtifactStat};
 
@@ -33,6 +35,30 @@ impl ArtifactStatName {
         };
         String::from(temp)
     }
+
+    pub fn from_mona(name: &str) -> Option<ArtifactStatName> {
+        match name {
+            "cureEffect" => Some(ArtifactStatName::HealingBonus),
+            "criticalDamage" => Some(ArtifactStatName::CriticalDamage),
+            "critical" => Some(ArtifactStatName::Critical),
+            "attackStatic" => Some(ArtifactStatName::Atk),
+            "attackPercentage" => Some(ArtifactStatName::AtkPercentage),
+            "elementalMastery" => Some(ArtifactStatName::ElementalMastery),
+            "recharge" => Some(ArtifactStatName::Recharge),
+            "lifePercentage" => Some(ArtifactStatName::HpPercentage),
+            "lifeStatic" => Some(ArtifactStatName::Hp),
+            "defendPercentage" => Some(ArtifactStatName::DefPercentage),
+            "defendStatic" => Some(ArtifactStatName::Def),
+            "thunderBonus" => Some(ArtifactStatName::ElectroBonus),
+            "fireBonus" => Some(ArtifactStatName::PyroBonus),
+            "waterBonus" => Some(ArtifactStatName::HydroBonus),
+            "iceBonus" => Some(ArtifactStatName::CryoBonus),
+            "windBonus" => Some(ArtifactStatName::AnemoBonus),
+            "rockBonus" => Some(ArtifactStatName::GeoBonus),
+            "physicalBonus" => Some(ArtifactStatName::PhysicalBonus),
+            _ => None,
+        }
+    }
 }
 
 impl ArtifactSetName {
@@ -76,6 +102,47 @@ impl ArtifactSetName {
         };
         String::from(temp)
     }
+
+    pub fn from_mona(name: &str) -> Option<ArtifactSetName> {
+        match name {
+            "archaicPetra" => Some(ArtifactSetName::ArchaicPetra),
+            "heartOfDepth" => Some(ArtifactSetName::HeartOfDepth),
+            "blizzardStrayer" => Some(ArtifactSetName::BlizzardStrayer),
+            "retracingBolide" => Some(ArtifactSetName::RetracingBolide),
+            "noblesseOblige" => Some(ArtifactSetName::NoblesseOblige),
+            "gladiatorFinale" => Some(ArtifactSetName::GladiatorFinale),
+            "maidenBeloved" => Some(ArtifactSetName::MaidenBeloved),
+            "viridescentVenerer" => Some(ArtifactSetName::ViridescentVenerer),
+            "lavaWalker" => Some(ArtifactSetName::LavaWalker),
+            "crimsonWitch" => Some(ArtifactSetName::CrimsonWitch),
+            "thunderSmoother" => Some(ArtifactSetName::ThunderSmoother),
+            "thunderingFury" => Some(ArtifactSetName::ThunderingFury),
+            "bloodstainedChivalry" => Some(ArtifactSetName::BloodstainedChivalry),
+            "wandererTroupe" => Some(ArtifactSetName::WandererTroupe),
+            "scholar" => Some(ArtifactSetName::Scholar),
+            "gambler" => Some(ArtifactSetName::Gambler),
+            "tinyMiracle" => Some(ArtifactSetName::TinyMiracle),
+            "martialArtist" => Some(ArtifactSetName::MartialArtist),
+            "braveHeart" => Some(ArtifactSetName::BraveHeart),
+            "resolutionOfSojourner" => Some(ArtifactSetName::ResolutionOfSojourner),
+            "defenderWill" => Some(ArtifactSetName::DefenderWill),
+            "berserker" => Some(ArtifactSetName::Berserker),
+            "instructor" => Some(ArtifactSetName::Instructor),
+            "exile" => Some(ArtifactSetName::Exile),
+            "adventurer" => Some(ArtifactSetName::Adventurer),
+            "luckyDog" => Some(ArtifactSetName::LuckyDog),
+            "travelingDoctor" => Some(ArtifactSetName::TravelingDoctor),
+            "prayersForWisdom" => Some(ArtifactSetName::PrayersForWisdom),
+            "prayersToSpringtime" => Some(ArtifactSetName::PrayersToSpringtime),
+            "prayersForIllumination" => Some(ArtifactSetName::PrayersForIllumination),
+            "prayersForDestiny" => Some(ArtifactSetName::PrayersForDestiny),
+            "paleFlame" => Some(ArtifactSetName::PaleFlame),
+            "tenacityOfTheMillelith" => Some(ArtifactSetName::TenacityOfTheMillelith),
+            "emblemOfSeveredFate" => Some(ArtifactSetName::EmblemOfSeveredFate),
+            "shimenawaReminiscence" => Some(ArtifactSetName::ShimenawaReminiscence),
+            _ => None,
+        }
+    }
 }
 
 impl ArtifactSlot {
@@ -89,13 +156,24 @@ impl ArtifactSlot {
         };
         String::from(temp)
     }
+
+    pub fn from_mona(name: &str) -> Option<ArtifactSlot> {
+        match name {
+            "flower" => Some(ArtifactSlot::Flower),
+            "feather" => Some(ArtifactSlot::Feather),
+            "sand" => Some(ArtifactSlot::Sand),
+            "cup" => Some(ArtifactSlot::Goblet),
+            "head" => Some(ArtifactSlot::Head),
+            _ => None,
+        }
+    }
 }
 
 impl Serialize for ArtifactStat {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
         let mut root = serializer.serialize_map(Some(2))?;
         root.serialize_entry("name", &self.name.to_mona());
-        root.serialize_entry("value", &self.value);
+        root.serialize_entry("value", &self.to_mona_value());
         root.end()
     }
 }
@@ -138,6 +216,61 @@ impl Serialize for MonaArtifact {
     }
 }
 
+impl ArtifactStat {
+    // mona expects percentage stats as fractions (10.5% crit rate is 0.105),
+    // which is also how they are stored internally. round away the float
+    // noise left by dividing the displayed value by 100
+    pub fn to_mona_value(&self) -> f64 {
+        if self.name.is_percentage() {
+            (self.value * 10000.0).round() / 10000.0
+        } else {
+            self.value
+        }
+    }
+
+    pub fn from_mona(value: &Value) -> Option<ArtifactStat> {
+        let name = ArtifactStatName::from_mona(value["name"].as_str()?)?;
+        // already a fraction for percentage stats, see to_mona_value
+        let value = value["value"].as_f64()?;
+
+        Some(ArtifactStat {
+            name,
+            value,
+        })
+    }
+}
+
+impl MonaArtifact {
+    // "equip" is not part of the mona format, but is read if present
+    pub fn from_mona(value: &Value) -> Option<MonaArtifact> {
+        let set_name = ArtifactSetName::from_mona(value["setName"].as_str()?)?;
+        let slot = ArtifactSlot::from_mona(value["position"].as_str()?)?;
+        let main_stat = ArtifactStat::from_mona(&value["mainTag"])?;
+
+        let mut sub_stats: Vec<ArtifactStat> = Vec::new();
+        for tag in value["normalTags"].as_array()?.iter() {
+            sub_stats.push(ArtifactStat::from_mona(tag)?);
+        }
+        if sub_stats.len() > 4 {
+            return None;
+        }
+        let mut sub_stats = sub_stats.into_iter();
+
+        Some(MonaArtifact {
+            set_name,
+            slot,
+            star: value["star"].as_u64()? as u32,
+            level: value["level"].as_u64()? as u32,
+            main_stat,
+            sub_stat_1: sub_stats.next(),
+            sub_stat_2: sub_stats.next(),
+            sub_stat_3: sub_stats.next(),
+            sub_stat_4: sub_stats.next(),
+            equip: value["equip"].as_str().map(String::from),
+        })
+    }
+}
+
 pub struct MonaFormat<'a> {
     version: String,
     flower: Vec<&'a MonaArtifact>,
@@ -189,7 +322,46 @@ impl<'a> MonaFormat<'a> {
         }
     }
 
+    pub fn load(path: String) -> Result<Vec<InternalArtifact>, String> {
+        let content = match std::fs::read_to_string(&path) {
+            Err(why) => return Err(format!("couldn't read {}: {}", path, why)),
+            Ok(s) => s,
+        };
+        let json: Value = match serde_json::from_str(&content) {
+            Err(why) => return Err(format!("couldn't parse {}: {}", path, why)),
+            Ok(v) => v,
+        };
+
+        let mut results: Vec<InternalArtifact> = Vec::new();
+        for &slot in ["flower", "feather", "sand", "cup", "head"].iter() {
+            let arts = match json[slot].as_array() {
+                Some(v) => v,
+                None => continue,
+            };
+            for art in arts.iter() {
+                match MonaArtifact::from_mona(art) {
+                    Some(a) => results.push(a),
+                    None => return Err(format!("invalid artifact in {}: {}", path, art)),
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    // path "-" writes to stdout
     pub fn save(&self, path: String) {
+        if path == "-" {
+            let s = serde_json::to_string(&self).unwrap();
+            let stdout = io::stdout();
+            let mut handle = stdout.lock();
+            match handle.write_all(s.as_bytes()) {
+                Err(why) => panic!("couldn't write to stdout: {}", why),
+                _ => {},
+            }
+            return;
+        }
+
         let mut file = match File::create(&path) {
             Err(why) => panic!("couldn't create {}: {}", path, why),
             Ok(file) => file,
@@ -202,3 +374,85 @@ impl<'a> MonaFormat<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use serde_json::json;
+
+    fn stat(s: &str) -> ArtifactStat {
+        ArtifactStat::from_zh_cn_raw(s).unwrap()
+    }
+
+    // as the scanner builds it from the recognized strings
+    fn scanned_artifact() -> InternalArtifact {
+        InternalArtifact {
+            set_name: ArtifactSetName::CrimsonWitch,
+            slot: ArtifactSlot::Flower,
+            star: 5,
+            level: 20,
+            main_stat: stat("生命值+4,780"),
+            sub_stat_1: Some(stat("暴击率+2.8%")),
+            sub_stat_2: Some(stat("暴击伤害+5.8%")),
+            sub_stat_3: Some(stat("攻击力+10.1%")),
+            sub_stat_4: Some(stat("元素精通+19")),
+            equip: None,
+        }
+    }
+
+    #[test]
+    fn test_to_mona_value() {
+        let crit = ArtifactStat::from_zh_cn_raw("暴击率+10.5%").unwrap();
+        assert_eq!(crit.name, ArtifactStatName::Critical);
+        assert_eq!(crit.to_mona_value(), 0.105);
+
+        let hp = ArtifactStat::from_zh_cn_raw("生命值+4,780").unwrap();
+        assert_eq!(hp.name, ArtifactStatName::Hp);
+        assert_eq!(hp.to_mona_value(), 4780.0);
+
+        let back = ArtifactStat::from_mona(&json!({ "name": "critical", "value": crit.to_mona_value() })).unwrap();
+        assert_eq!(back.name, ArtifactStatName::Critical);
+        assert_eq!(back.value, 0.105);
+    }
+
+    #[test]
+    fn test_normal_tags_length() {
+        let full = scanned_artifact();
+        let json = serde_json::to_value(&full).unwrap();
+        let tags = json["normalTags"].as_array().unwrap();
+        assert_eq!(tags.len(), 4);
+        assert!(tags.iter().all(|t| t["name"].is_string() && t["value"].is_number()));
+
+        let one = InternalArtifact {
+            star: 3,
+            level: 0,
+            sub_stat_2: None,
+            sub_stat_3: None,
+            sub_stat_4: None,
+            ..scanned_artifact()
+        };
+        let json = serde_json::to_value(&one).unwrap();
+        let tags = json["normalTags"].as_array().unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0]["name"], "critical");
+    }
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let art = scanned_artifact();
+        // 5.8 / 100 is not exactly 0.058
+        assert_ne!(art.sub_stat_2.as_ref().unwrap().value, 0.058);
+
+        let path = std::env::temp_dir().join("yas_test_mona_round_trip.json");
+        let path = path.to_str().unwrap().to_string();
+        let results = vec![art.clone()];
+        MonaFormat::new(&results).save(path.clone());
+        let loaded = MonaFormat::load(path.clone()).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded, results);
+        let known: HashSet<InternalArtifact> = loaded.into_iter().collect();
+        assert!(known.contains(&art));
+    }
+}
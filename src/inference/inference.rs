@@ -58,6 +58,12 @@ impl CRNNModel {
     }
 
     pub fn inference_string(&mut self, img: &RawImage) -> String {
+        self.inference_string_with_confidence(img).0
+    }
+
+    // the model outputs log probabilities, the confidence is the lowest
+    // probability among the emitted characters, 1.0 if nothing is emitted
+    pub fn inference_string_with_confidence(&mut self, img: &RawImage) -> (String, f64) {
         let tensor: Tensor = tract_ndarray::Array4::from_shape_fn((1, 1, 32, 384), |(_, _, y, x)| {
             let index = img.w * y as u32 + x as u32;
             img.data[index as usize]
@@ -70,6 +76,7 @@ impl CRNNModel {
 
         let mut ans = String::new();
         let mut last_word = String::new();
+        let mut confidence = 1.0_f64;
         for i in 0..shape[0] {
             let mut max_index = 0;
             let mut max_value = -1.0;
@@ -83,11 +90,12 @@ impl CRNNModel {
             let word = &self.index_2_word[max_index];
             if *word != last_word && word != "-" {
                 ans = ans + word;
+                confidence = confidence.min((arr[[i, 0, max_index]] as f64).exp());
             }
 
             last_word = word.clone();
         }
 
-        ans
+        (ans, confidence)
     }
 }
\ No newline at end of file
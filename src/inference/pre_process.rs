@@ -145,7 +145,7 @@ pub fn raw_to_img(im: &RawImage) -> GrayImage {
     let img = ImageBuffer::from_fn(width, height, |x, y| {
         let index = get_index(width, x, y);
         let p = data[index];
-        let pixel = (p * 255.0) as u32;
+        let pixel = (p * 255.0) as i32;
         let pixel: u8 = if pixel > 255 {
             255
         } else if pixel < 0 {
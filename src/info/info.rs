@@ -1,3 +1,6 @@
+use serde_json::Value;
+use image::{RgbImage, Rgb};
+
 use crate::common::{PixelRect, PixelRectBound};
 
 #[derive(Clone)]
@@ -63,7 +66,45 @@ fn get_rect(rect: (u32, u32, u32, u32), src_h: u32, src_w: u32, dst_h: u32, dst_
     }
 }
 
+fn parse_rect(value: &Value) -> Option<PixelRectBound> {
+    let arr = value.as_array()?;
+    if arr.len() != 4 {
+        return None;
+    }
+
+    Some(PixelRectBound {
+        left: arr[0].as_i64()? as i32,
+        top: arr[1].as_i64()? as i32,
+        right: arr[2].as_i64()? as i32,
+        bottom: arr[3].as_i64()? as i32,
+    })
+}
+
+fn rect_inside(inner: &PixelRectBound, outer: &PixelRectBound) -> bool {
+    inner.left < inner.right && inner.top < inner.bottom
+        && inner.left >= outer.left && inner.right <= outer.right
+        && inner.top >= outer.top && inner.bottom <= outer.bottom
+}
+
 impl ScanInfo {
+    // pick the layout matching the aspect ratio of the game's client area
+    pub fn from_rect(rect: &PixelRect) -> Result<ScanInfo, String> {
+        let width = rect.width as u32;
+        let height = rect.height as u32;
+        let left = rect.left as u32;
+        let top = rect.top as u32;
+
+        if rect.height * 16 == rect.width * 9 {
+            Ok(ScanInfo::from_16_9(width, height, left, top))
+        } else if rect.height * 8 == rect.width * 5 {
+            Ok(ScanInfo::from_8_5(width, height, left, top))
+        } else if rect.height * 4 == rect.width * 3 {
+            Ok(ScanInfo::from_4_3(width, height, left, top))
+        } else {
+            Err(String::from("不支持的分辨率"))
+        }
+    }
+
     pub fn from_16_9(width: u32, height: u32, left: u32, top: u32) -> ScanInfo {
         let w: u32 = 1600;
         let h: u32 = 900;
@@ -222,4 +263,185 @@ impl ScanInfo {
 
         info
     }
+
+    // replace measured positions with explicit ones, relative to the window, e.g.
+    // {"pool_position": [1181, 117, 1210, 456], "flag_x": 224}
+    pub fn apply_override(&mut self, json: &Value) -> Result<(), String> {
+        let obj = json.as_object().ok_or(String::from("override must be a json object"))?;
+        for (key, value) in obj.iter() {
+            let rect = || parse_rect(value).ok_or(format!("invalid rect for {}", key));
+            let scalar = || value.as_u64().map(|x| x as u32).ok_or(format!("invalid value for {}", key));
+            match key.as_str() {
+                "title_position" => self.title_position = rect()?,
+                "main_stat_name_position" => self.main_stat_name_position = rect()?,
+                "main_stat_value_position" => self.main_stat_value_position = rect()?,
+                "level_position" => self.level_position = rect()?,
+                "panel_position" => self.panel_position = rect()?,
+                "sub_stat1_position" => self.sub_stat1_position = rect()?,
+                "sub_stat2_position" => self.sub_stat2_position = rect()?,
+                "sub_stat3_position" => self.sub_stat3_position = rect()?,
+                "sub_stat4_position" => self.sub_stat4_position = rect()?,
+                "equip_position" => self.equip_position = rect()?,
+                "art_count_position" => self.art_count_position = rect()?,
+                "pool_position" => self.pool_position = rect()?,
+                "art_width" => self.art_width = scalar()?,
+                "art_height" => self.art_height = scalar()?,
+                "art_gap_x" => self.art_gap_x = scalar()?,
+                "art_gap_y" => self.art_gap_y = scalar()?,
+                "art_row" => self.art_row = scalar()?,
+                "art_col" => self.art_col = scalar()?,
+                "left_margin" => self.left_margin = scalar()?,
+                "top_margin" => self.top_margin = scalar()?,
+                "flag_x" => self.flag_x = scalar()?,
+                "flag_y" => self.flag_y = scalar()?,
+                "star_x" => self.star_x = scalar()?,
+                "star_y" => self.star_y = scalar()?,
+                _ => return Err(format!("unknown key: {}", key)),
+            }
+        }
+
+        self.validate()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let window = PixelRectBound {
+            left: 0,
+            top: 0,
+            right: self.width as i32,
+            bottom: self.height as i32,
+        };
+
+        for &(name, rect) in [
+            ("panel_position", &self.panel_position),
+            ("art_count_position", &self.art_count_position),
+            ("pool_position", &self.pool_position),
+        ].iter() {
+            if !rect_inside(rect, &window) {
+                return Err(format!("{} is outside the window", name));
+            }
+        }
+
+        // these are cropped out of the panel capture
+        for &(name, rect) in [
+            ("title_position", &self.title_position),
+            ("main_stat_name_position", &self.main_stat_name_position),
+            ("main_stat_value_position", &self.main_stat_value_position),
+            ("level_position", &self.level_position),
+            ("sub_stat1_position", &self.sub_stat1_position),
+            ("sub_stat2_position", &self.sub_stat2_position),
+            ("sub_stat3_position", &self.sub_stat3_position),
+            ("sub_stat4_position", &self.sub_stat4_position),
+            ("equip_position", &self.equip_position),
+        ].iter() {
+            if !rect_inside(rect, &self.panel_position) {
+                return Err(format!("{} is outside panel_position", name));
+            }
+        }
+
+        if self.flag_x >= self.width || self.flag_y >= self.height {
+            return Err(String::from("flag position is outside the window"));
+        }
+        if self.star_x >= self.width || self.star_y >= self.height {
+            return Err(String::from("star position is outside the window"));
+        }
+        if self.art_row == 0 || self.art_col == 0 {
+            return Err(String::from("art_row and art_col must be positive"));
+        }
+
+        Ok(())
+    }
+
+    // outline every region used by the scanner on a capture of the whole window
+    pub fn draw_regions(&self, img: &mut RgbImage) {
+        let red = Rgb([255, 0, 0]);
+        let green = Rgb([0, 255, 0]);
+        let blue = Rgb([0, 0, 255]);
+
+        draw_rect(img, &self.panel_position, blue);
+        draw_rect(img, &self.art_count_position, red);
+        draw_rect(img, &self.pool_position, red);
+        for rect in [
+            &self.title_position,
+            &self.main_stat_name_position,
+            &self.main_stat_value_position,
+            &self.level_position,
+            &self.sub_stat1_position,
+            &self.sub_stat2_position,
+            &self.sub_stat3_position,
+            &self.sub_stat4_position,
+            &self.equip_position,
+        ].iter() {
+            draw_rect(img, rect, red);
+        }
+
+        for row in 0..self.art_row {
+            for col in 0..self.art_col {
+                let left = self.left_margin + (self.art_width + self.art_gap_x) * col;
+                let top = self.top_margin + (self.art_height + self.art_gap_y) * row;
+                let cell = PixelRectBound {
+                    left: left as i32,
+                    top: top as i32,
+                    right: (left + self.art_width) as i32,
+                    bottom: (top + self.art_height) as i32,
+                };
+                draw_rect(img, &cell, green);
+            }
+        }
+
+        draw_cross(img, self.flag_x, self.flag_y, red);
+        draw_cross(img, self.star_x, self.star_y, red);
+    }
+}
+
+fn put_pixel_checked(img: &mut RgbImage, x: i32, y: i32, color: Rgb<u8>) {
+    if x >= 0 && y >= 0 && (x as u32) < img.width() && (y as u32) < img.height() {
+        img.put_pixel(x as u32, y as u32, color);
+    }
+}
+
+fn draw_rect(img: &mut RgbImage, rect: &PixelRectBound, color: Rgb<u8>) {
+    for x in rect.left..rect.right {
+        put_pixel_checked(img, x, rect.top, color);
+        put_pixel_checked(img, x, rect.bottom - 1, color);
+    }
+    for y in rect.top..rect.bottom {
+        put_pixel_checked(img, rect.left, y, color);
+        put_pixel_checked(img, rect.right - 1, y, color);
+    }
+}
+
+fn draw_cross(img: &mut RgbImage, x: u32, y: u32, color: Rgb<u8>) {
+    let (x, y) = (x as i32, y as i32);
+    for d in -5..=5 {
+        put_pixel_checked(img, x + d, y, color);
+        put_pixel_checked(img, x, y + d, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, width: i32, height: i32) -> PixelRect {
+        PixelRect { left, top, width, height }
+    }
+
+    #[test]
+    fn test_from_rect_720p() {
+        let info = ScanInfo::from_rect(&rect(100, 50, 1280, 720)).unwrap();
+        assert!(info.validate().is_ok());
+        // the 16:9 layout, scaled down from 1600x900
+        assert_eq!((info.art_row, info.art_col), (5, 7));
+        assert_eq!((info.width, info.height, info.left, info.top), (1280, 720, 100, 50));
+        let reference = ScanInfo::from_rect(&rect(0, 0, 1600, 900)).unwrap();
+        assert!(info.panel_position.right < reference.panel_position.right);
+        assert!(info.art_width < reference.art_width);
+    }
+
+    #[test]
+    fn test_from_rect_aspect_ratios() {
+        assert_eq!(ScanInfo::from_rect(&rect(0, 0, 1440, 900)).unwrap().art_row, 6);
+        assert_eq!(ScanInfo::from_rect(&rect(0, 0, 1280, 960)).unwrap().art_row, 7);
+        assert!(ScanInfo::from_rect(&rect(0, 0, 1000, 1000)).is_err());
+    }
 }
\ No newline at end of file
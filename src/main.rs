@@ -1,104 +1,134 @@
-use std::time::{Duration, Instant, SystemTime};
+use std::time::SystemTime;
 use std::io::stdin;
+use std::process;
 
-use yas::common::utils;
-use yas::capture::{capture_absolute, capture_absolute_image};
-use yas::inference::pre_process::{to_gray, raw_to_img, normalize, crop, pre_process, image_to_raw};
-use yas::info::info;
-
-use winapi::um::winuser::{SetForegroundWindow, GetDpiForSystem, SetThreadDpiAwarenessContext, ShowWindow, SW_SHOW, SW_RESTORE};
+use yas::common::{utils, exit_code};
+use yas::common::log_file::LogFileWriter;
 
 use clap::{Arg, App};
 
-use image::{ImageBuffer, Pixel};
-use image::imageops::grayscale;
-use yas::common::{RawImage, PixelRect};
-use yas::scanner::yas_scanner::{YasScanner, YasScannerConfig};
-use yas::inference::inference::CRNNModel;
+use yas::scanner::yas_scanner::{YasScannerConfig, scan_report, diagnose};
 use yas::expo::mona_uranai::MonaFormat;
-use env_logger::{Env, Builder, Target};
-use log::{info, LevelFilter};
-use winapi::shared::windef::DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE;
-
-fn open_local(path: String) -> RawImage {
-    let img = image::open(path).unwrap();
-    let img = grayscale(&img);
-    let raw_img = image_to_raw(img);
-
-    raw_img
+use yas::artifact::audit::{self, AuditSeverity};
+use env_logger::{Builder, Target};
+use log::{info, error, LevelFilter};
+
+// MB to bytes, None if it is not a positive whole number of MB
+fn parse_log_file_max_size(s: &str) -> Option<u64> {
+    match s.parse::<u64>() {
+        Ok(v) if v > 0 => v.checked_mul(1024 * 1024),
+        _ => None,
+    }
 }
 
 fn main() {
-    // let mut raw = open_local(String::from("data/test/15.png"));
-    // let raw = pre_process(raw);
-    // // normalize(&mut raw, true);
-    // let img = raw_to_img(&raw);
-    // img.save("test.png").unwrap();
-
-    Builder::new().filter_level(LevelFilter::Info).init();
-
-    if !utils::is_admin() {
-        utils::error_and_quit("请以管理员身份运行该程序")
-    }
-
     let matches = App::new("YAS - 原神圣遗物导出器")
         .version("0.1.0")
         .author("wormtql <584130248@qq.com>")
         .about("Genshin Impact Artifact Exporter")
+        .arg(Arg::with_name("config").long("config").takes_value(true).help("从json文件读取扫描参数，命令行参数优先"))
         .arg(Arg::with_name("max-row").long("max-row").takes_value(true).help("最大扫描行数"))
         .arg(Arg::with_name("capture-only").long("capture-only").required(false).takes_value(false).help("只保存截图，不进行扫描，debug专用"))
+        .arg(Arg::with_name("debug-regions").long("debug-regions").takes_value(true).help("截取游戏窗口并标出所有识别区域，保存为png后退出，debug专用"))
         .arg(Arg::with_name("min-star").long("min-star").takes_value(true).help("最小星级").min_values(1).max_values(5))
+        .arg(Arg::with_name("min-level").long("min-level").takes_value(true).help("最小等级"))
+        .arg(Arg::with_name("sets").long("sets").takes_value(true).help("只导出指定套装，用逗号分隔，如 CrimsonWitch,炽烈的炎之魔女"))
+        .arg(Arg::with_name("limit-sets-to-scan").long("limit-sets-to-scan").takes_value(true).help("扫描到N个--sets指定套装的圣遗物后停止"))
+        .arg(Arg::with_name("equipped").long("equipped").required(false).takes_value(false).conflicts_with("unequipped").help("只导出已装备的圣遗物"))
+        .arg(Arg::with_name("unequipped").long("unequipped").required(false).takes_value(false).help("只导出未装备的圣遗物"))
+        .arg(Arg::with_name("sort").long("sort").required(false).takes_value(false).help("按套装、位置、星级、主词条排序导出结果"))
+        .arg(Arg::with_name("check-tearing").long("check-tearing").required(false).takes_value(false).help("两次截图一致后再识别，避免截到切换动画"))
+        .arg(Arg::with_name("capture-retry").long("capture-retry").takes_value(true).help("截图失败时的重试次数，默认3"))
+        .arg(Arg::with_name("min-confidence").long("min-confidence").takes_value(true).help("识别置信度(0~1)低于该值时丢弃该圣遗物，默认0"))
+        .arg(Arg::with_name("stop-at-known").long("stop-at-known").takes_value(true).help("读取上次导出的mona.json，连续遇到已导出的圣遗物时停止扫描（背包需按入手顺序排列）"))
+        .arg(Arg::with_name("stop-at-known-run").long("stop-at-known-run").takes_value(true).help("连续遇到多少个已导出的圣遗物时停止，默认5"))
+        .arg(Arg::with_name("audit").long("audit").required(false).takes_value(false).help("导出前检查结果中可疑的圣遗物"))
+        .arg(Arg::with_name("strict").long("strict").required(false).takes_value(false).help("检查发现不可能存在的圣遗物时不导出，并以非零值退出，包含--audit"))
+        .arg(Arg::with_name("output").long("output").short("o").takes_value(true).help("输出文件，- 表示输出到标准输出").default_value("mona.json"))
         .arg(Arg::with_name("max-wait-switch-artifact").long("max-wait-switch-artifact").takes_value(true).min_values(10).help("切换圣遗物最大等待时间(ms)"))
+        .arg(Arg::with_name("color-metric").long("color-metric").takes_value(true).possible_values(&["euclidean", "redmean"]).help("翻页时比较颜色的方式，默认euclidean"))
+        .arg(Arg::with_name("color-threshold").long("color-threshold").takes_value(true).help("颜色差异小于该值视为相同，默认euclidean为20，redmean为60"))
+        .arg(Arg::with_name("pool-threshold").long("pool-threshold").takes_value(true).help("判断圣遗物已切换的最小变化量，默认0.000001"))
+        .arg(Arg::with_name("max-duration").long("max-duration").takes_value(true).help("最大扫描时间(秒)，超时后导出已识别的圣遗物"))
+        .arg(Arg::with_name("resume").long("resume").required(false).takes_value(false).help("从上次中断的位置继续扫描"))
+        .arg(Arg::with_name("start-row").long("start-row").takes_value(true).conflicts_with("resume").help("从第N行开始扫描（从0开始计数），跳过上面的行"))
+        .arg(Arg::with_name("calibrate").long("calibrate").required(false).takes_value(false).help("测量切换圣遗物的耗时，并给出建议的等待时间"))
+        .arg(Arg::with_name("benchmark").long("benchmark").takes_value(true).help("对当前选中的圣遗物重复截图和识别N次，统计耗时"))
+        .arg(Arg::with_name("single").long("single").required(false).takes_value(false).help("不移动鼠标，只识别当前选中的圣遗物并输出结果，debug专用"))
+        .arg(Arg::with_name("click-offset-x").long("click-offset-x").takes_value(true).help("点击位置在圣遗物格子中的横向比例(0~1，不含1)，默认0.5"))
+        .arg(Arg::with_name("click-offset-y").long("click-offset-y").takes_value(true).help("点击位置在圣遗物格子中的纵向比例(0~1，不含1)，默认0.25"))
+        .arg(Arg::with_name("info-override").long("info-override").takes_value(true).help("从json文件读取截图位置，覆盖自动计算的值，配合--capture-only检查"))
+        .arg(Arg::with_name("grid-rows").long("grid-rows").takes_value(true).help("背包一屏显示的行数，覆盖根据分辨率计算的值"))
+        .arg(Arg::with_name("grid-cols").long("grid-cols").takes_value(true).help("背包一屏显示的列数，覆盖根据分辨率计算的值"))
+        .arg(Arg::with_name("detect-grid").long("detect-grid").required(false).takes_value(false).help("扫描前测量圣遗物格子的间距，与预设值相差较大时使用测量值"))
+        .arg(Arg::with_name("single-capture").long("single-capture").required(false).takes_value(false).help("只截取圣遗物详情面板，从中检测切换和星级，截图较慢时可减少截图次数，可用--benchmark比较"))
+        .arg(Arg::with_name("log-file").long("log-file").takes_value(true).help("同时将日志写入文件"))
+        .arg(Arg::with_name("log-file-max-size").long("log-file-max-size").takes_value(true)
+            .validator(|v| parse_log_file_max_size(&v).map(|_| ()).ok_or(format!("日志文件大小应为正整数(MB)：{}", v)))
+            .help("日志文件超过该大小(MB)时轮换"))
         .get_matches();
-    let config = YasScannerConfig::from_match(matches);
 
-    unsafe { SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE); }
-    let hwnd = match utils::find_window(String::from("原神")) {
-        Err(s) => {
-            utils::error_and_quit("未找到原神窗口，请确认原神已经开启");
-        },
-        Ok(h) => h,
-    };
-
-    unsafe { ShowWindow(hwnd, SW_RESTORE); }
-    // utils::sleep(1000);
-    unsafe { SetForegroundWindow(hwnd); }
-    utils::sleep(1000);
+    let mut builder = Builder::new();
+    builder.filter_level(LevelFilter::Info);
+    if let Some(path) = matches.value_of("log-file") {
+        let max_size = matches.value_of("log-file-max-size")
+            .and_then(parse_log_file_max_size);
+        match LogFileWriter::new(String::from(path), max_size) {
+            Ok(w) => { builder.target(Target::Pipe(Box::new(w))); },
+            Err(e) => eprintln!("cannot open log file {}: {}", path, e),
+        }
+    }
+    builder.init();
 
-    let mut rect = utils::get_client_rect(hwnd).unwrap();
+    if !utils::is_admin() {
+        utils::error_and_quit("请以管理员身份运行该程序")
+    }
 
-    // rect.scale(1.25);
-    info!("detected left: {}", rect.left);
-    info!("detected top: {}", rect.top);
-    info!("detected width: {}", rect.width);
-    info!("detected height: {}", rect.height);
+    let output = String::from(matches.value_of("output").unwrap());
+    let strict = matches.is_present("strict");
+    let run_audit = strict || matches.is_present("audit");
+    let config = YasScannerConfig::from_match(matches);
 
-    let mut info: info::ScanInfo;
-    if rect.height * 16 == rect.width * 9 {
-        info = info::ScanInfo::from_16_9(rect.width as u32, rect.height as u32, rect.left as u32, rect.top as u32);
-    } else if rect.height * 8 == rect.width * 5 {
-        info = info::ScanInfo::from_8_5(rect.width as u32, rect.height as u32, rect.left as u32, rect.top as u32);
-    } else if rect.height * 4 == rect.width * 3 {
-        info = info::ScanInfo::from_4_3(rect.width as u32, rect.height as u32, rect.left as u32, rect.top as u32);
-    } else {
-        utils::error_and_quit("不支持的分辨率");
+    // debug modes export nothing, keep the previous export untouched
+    if config.is_diagnostic() {
+        if let Err(e) = diagnose(config) {
+            utils::error_and_quit_with_code(&e.to_string(), e.exit_code());
+        }
+        info!("结束，请按Enter退出");
+        let mut s = String::new();
+        stdin().read_line(&mut s).ok();
+        process::exit(exit_code::SUCCESS);
     }
 
-    let mut scanner = YasScanner::new(info.clone(), config);
-
     let now = SystemTime::now();
-    let results = scanner.start();
+    let report = match scan_report(config) {
+        Ok(v) => v,
+        Err(e) => utils::error_and_quit_with_code(&e.to_string(), e.exit_code()),
+    };
+    let results = report.artifacts;
+    if run_audit {
+        let findings = audit::audit(&results);
+        audit::log_audit(&results, &findings);
+        if strict && findings.iter().any(|f| f.severity >= AuditSeverity::Error) {
+            error!("检查发现错误的圣遗物，未导出");
+            process::exit(exit_code::AUDIT_FAILED);
+        }
+    }
     let mona = MonaFormat::new(&results);
     let t = now.elapsed().unwrap().as_secs_f64();
     info!("time: {}s", t);
-    mona.save(String::from("mona.json"));
+    mona.save(output.clone());
     // let info = info;
     // let img = info.art_count_position.capture_relative(&info).unwrap();
 
     // let mut inference = CRNNModel::new(String::from("model_training.onnx"), String::from("index_2_word.json"));
     // let s = inference.inference_string(&img);
     // println!("{}", s);
-    info!("识别结束，请按Enter退出");
-    let mut s = String::new();
-    stdin().read_line(&mut s);
+    if output != "-" {
+        // don't block scripts reading from the pipe
+        info!("识别结束，请按Enter退出");
+        let mut s = String::new();
+        stdin().read_line(&mut s).ok();
+    }
+    process::exit(report.status.exit_code());
 }
@@ -0,0 +1,61 @@
+use std::fs;
+
+use serde_json::{json, Value};
+
+use crate::artifact::internal_artifact::InternalArtifact;
+
+pub const CHECKPOINT_PATH: &str = "yas_checkpoint.json";
+
+// save a checkpoint every this many rows
+pub const CHECKPOINT_INTERVAL_ROWS: u32 = 5;
+
+pub struct Checkpoint {
+    // artifact count detected when the checkpoint was written
+    pub count: u32,
+    // items already handed to the recognizer, always a multiple of the column count
+    pub processed: u32,
+    pub results: Vec<InternalArtifact>,
+}
+
+fn artifact_to_value(art: &InternalArtifact) -> Value {
+    let mut value = serde_json::to_value(art).unwrap();
+    if let Some(ref equip) = art.equip {
+        value["equip"] = Value::from(equip.clone());
+    }
+
+    value
+}
+
+pub fn save_checkpoint(path: &str, count: u32, processed: u32, results: &[InternalArtifact]) -> Result<(), String> {
+    let artifacts: Vec<Value> = results.iter().map(artifact_to_value).collect();
+    let json = json!({
+        "count": count,
+        "processed": processed,
+        "artifacts": artifacts,
+    });
+
+    fs::write(path, json.to_string()).map_err(|e| e.to_string())
+}
+
+pub fn load_checkpoint(path: &str) -> Result<Checkpoint, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let json: Value = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+
+    let count = json["count"].as_u64().ok_or(String::from("missing count"))? as u32;
+    let processed = json["processed"].as_u64().ok_or(String::from("missing processed"))? as u32;
+    let artifacts = json["artifacts"].as_array().ok_or(String::from("missing artifacts"))?;
+
+    let mut results: Vec<InternalArtifact> = Vec::new();
+    for art in artifacts.iter() {
+        match InternalArtifact::from_mona(art) {
+            Some(a) => results.push(a),
+            None => return Err(format!("invalid artifact: {}", art)),
+        }
+    }
+
+    Ok(Checkpoint {
+        count,
+        processed,
+        results,
+    })
+}
@@ -1 +1,2 @@
-pub mod yas_scanner;
\ No newline at end of file
+pub mod yas_scanner;
+pub mod checkpoint;
@@ -5,39 +5,404 @@ use std::convert::From;
 use std::collections::HashSet;
 use std::io::stdin;
 use std::fs;
+use std::fmt;
 
 use enigo::*;
 use log::{info, warn, error, debug};
 use clap::{ArgMatches};
+use serde_json::Value;
+use serde::{de, Deserialize, Deserializer};
+use winapi::shared::windef::{HWND, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE};
+use winapi::um::winuser::{SetForegroundWindow, SetThreadDpiAwarenessContext, ShowWindow, SW_RESTORE};
 
 use crate::info::info::ScanInfo;
 use crate::inference::inference::CRNNModel;
-use crate::common::{utils, RawImage, PixelRect, RawCaptureImage, PixelRectBound};
-use crate::capture;
-use crate::common::color::Color;
-use crate::artifact::internal_artifact::{ArtifactSlot, ArtifactStat, ArtifactSetName, InternalArtifact};
-
+use crate::common::{utils, exit_code, RawImage, PixelRect, RawCaptureImage, PixelRectBound};
+use crate::capture::{Capturer, ScreenCapturer};
+use crate::common::color::{Color, ColorMetric};
+use crate::artifact::internal_artifact::{ArtifactSlot, ArtifactStat, ArtifactSetName, InternalArtifact, expected_sub_stat_count};
+use crate::artifact::summary;
+use crate::scanner::checkpoint;
+use crate::expo::mona_uranai::MonaFormat;
+
+// the in-game artifact inventory limit
+const MAX_ART_COUNT: u32 = 1500;
+
+// share of pixels whose color differs, by the configured metric, below
+// which two panel captures are the same
+const TEARING_TOLERANCE: f64 = 0.01;
+const TEARING_CHECK_DELAY: u32 = 10;
+const MAX_TEARING_RETRY: u32 = 5;
+
+// px, measured grid pitches closer than this to the configured ones are ignored
+const GRID_GAP_TOLERANCE: i32 = 2;
+
+// scroll_rows stops this many ticks short of the estimate and align_row
+// looks no further down, capped below one row so the next row is never reached
+const SCROLL_SHORT_TICKS: u32 = 3;
+
+// ms, doubled after every failed capture up to the max
+const CAPTURE_RETRY_DELAY: u32 = 20;
+const MAX_CAPTURE_RETRY_DELAY: u32 = 1000;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
 pub struct YasScannerConfig {
-    max_row: u32,
-    capture_only: bool,
-    min_star: u32,
-    max_wait_switch_artifact: u32,
+    pub max_row: u32,
+    pub capture_only: bool,
+    // save a window capture with all scan regions outlined, then exit
+    pub debug_regions: Option<String>,
+    pub min_star: u32,
+    pub min_level: u32,
+    #[serde(deserialize_with = "deserialize_sets")]
+    pub sets: Option<Vec<ArtifactSetName>>,
+    // stop scanning once this many artifacts of `sets` are collected
+    pub limit_sets_to_scan: Option<u32>,
+    // Some(true) keeps only equipped artifacts, Some(false) only unequipped ones
+    pub equipped: Option<bool>,
+    pub sort: bool,
+    // capture the panel twice and retry while the captures differ
+    pub check_tearing: bool,
+    // retries of a failed capture before the scan is aborted
+    pub capture_retry: u32,
+    // reject detections where any field is recognized below this probability
+    pub min_confidence: f64,
+    // a previous mona export; stop once this many known artifacts are seen in a row
+    pub stop_at_known: Option<String>,
+    pub stop_at_known_run: u32,
+    pub max_wait_switch_artifact: u32,
+    // how the row flag color is compared while scrolling, the threshold
+    // defaults to the metric's own default
+    pub color_metric: ColorMetric,
+    pub color_threshold: Option<u32>,
+    // the pool value must change by more than this for an artifact switch
+    pub pool_threshold: f64,
+    // seconds, the scan is interrupted with partial results once exceeded
+    pub max_duration: Option<u32>,
+    pub calibrate: bool,
+    pub resume: bool,
+    // skip the rows above, counted from 0
+    pub start_row: Option<u32>,
+    pub benchmark: Option<u32>,
+    // recognize the selected artifact once and exit
+    pub single: bool,
+    // where to click inside an item, as a fraction of the item size
+    #[serde(deserialize_with = "deserialize_click_offset")]
+    pub click_offset_x: f64,
+    #[serde(deserialize_with = "deserialize_click_offset")]
+    pub click_offset_y: f64,
+    // json file with positions overriding the detected layout
+    pub info_override: Option<String>,
+    // replace the number of visible rows/columns computed from the resolution
+    pub grid_rows: Option<u32>,
+    pub grid_cols: Option<u32>,
+    // measure the gaps between cells before scanning
+    pub detect_grid: bool,
+    // detect switches on panel captures and recognize the item from the
+    // last one, instead of capturing the pool and the panel separately
+    pub single_capture: bool,
+    // the --config file, --calibrate writes its result back into it
+    #[serde(skip)]
+    pub config_file: Option<String>,
+}
+
+impl Default for YasScannerConfig {
+    fn default() -> YasScannerConfig {
+        YasScannerConfig {
+            max_row: 1000,
+            capture_only: false,
+            debug_regions: None,
+            min_star: 4,
+            min_level: 0,
+            sets: None,
+            limit_sets_to_scan: None,
+            equipped: None,
+            sort: false,
+            check_tearing: false,
+            capture_retry: 3,
+            min_confidence: 0.0,
+            stop_at_known: None,
+            stop_at_known_run: 5,
+            max_wait_switch_artifact: 500,
+            color_metric: ColorMetric::Euclidean,
+            color_threshold: None,
+            pool_threshold: 0.000001,
+            max_duration: None,
+            calibrate: false,
+            resume: false,
+            start_row: None,
+            benchmark: None,
+            single: false,
+            click_offset_x: 0.5,
+            click_offset_y: 0.25,
+            info_override: None,
+            grid_rows: None,
+            grid_cols: None,
+            detect_grid: false,
+            single_capture: false,
+            config_file: None,
+        }
+    }
 }
 
 impl YasScannerConfig {
+    // precedence: command line > --config file > defaults
     pub fn from_match(matches: ArgMatches) -> YasScannerConfig {
-        YasScannerConfig {
-            max_row: matches.value_of("max-row").unwrap_or("1000").parse::<u32>().unwrap(),
-            capture_only: matches.is_present("capture-only"),
-            min_star: matches.value_of("min-star").unwrap_or("4").parse::<u32>().unwrap(),
-            max_wait_switch_artifact: matches.value_of("max-wait-switch-artifact").unwrap_or("500").parse::<u32>().unwrap(),
+        let mut config = match matches.value_of("config") {
+            Some(path) => match YasScannerConfig::from_file(path) {
+                Ok(c) => c,
+                Err(e) => utils::error_and_quit(&e),
+            },
+            None => YasScannerConfig::default(),
+        };
+        config.config_file = matches.value_of("config").map(String::from);
+
+        if let Some(v) = matches.value_of("max-row") {
+            config.max_row = v.parse::<u32>().unwrap();
+        }
+        if matches.is_present("capture-only") {
+            config.capture_only = true;
+        }
+        if let Some(v) = matches.value_of("debug-regions") {
+            config.debug_regions = Some(String::from(v));
+        }
+        if let Some(v) = matches.value_of("min-star") {
+            config.min_star = v.parse::<u32>().unwrap();
+        }
+        if let Some(v) = matches.value_of("min-level") {
+            config.min_level = v.parse::<u32>().unwrap();
+        }
+        if let Some(v) = matches.value_of("sets") {
+            config.sets = Some(parse_sets(v));
+        }
+        if let Some(v) = matches.value_of("limit-sets-to-scan") {
+            config.limit_sets_to_scan = Some(v.parse::<u32>().unwrap());
+        }
+        if matches.is_present("equipped") {
+            config.equipped = Some(true);
+        } else if matches.is_present("unequipped") {
+            config.equipped = Some(false);
+        }
+        if matches.is_present("sort") {
+            config.sort = true;
+        }
+        if matches.is_present("check-tearing") {
+            config.check_tearing = true;
+        }
+        if let Some(v) = matches.value_of("capture-retry") {
+            config.capture_retry = v.parse::<u32>().unwrap();
+        }
+        if let Some(v) = matches.value_of("min-confidence") {
+            config.min_confidence = v.parse::<f64>().unwrap();
+        }
+        if let Some(v) = matches.value_of("stop-at-known") {
+            config.stop_at_known = Some(String::from(v));
+        }
+        if let Some(v) = matches.value_of("stop-at-known-run") {
+            config.stop_at_known_run = v.parse::<u32>().unwrap().max(1);
+        }
+        if let Some(v) = matches.value_of("max-wait-switch-artifact") {
+            config.max_wait_switch_artifact = v.parse::<u32>().unwrap();
+        }
+        if let Some(v) = matches.value_of("color-metric") {
+            config.color_metric = parse_color_metric(v);
+        }
+        if let Some(v) = matches.value_of("color-threshold") {
+            config.color_threshold = Some(v.parse::<u32>().unwrap());
+        }
+        if let Some(v) = matches.value_of("pool-threshold") {
+            config.pool_threshold = v.parse::<f64>().unwrap();
+        }
+        if let Some(v) = matches.value_of("max-duration") {
+            config.max_duration = Some(v.parse::<u32>().unwrap());
+        }
+        if matches.is_present("calibrate") {
+            config.calibrate = true;
+        }
+        if matches.is_present("resume") {
+            config.resume = true;
+        }
+        if let Some(v) = matches.value_of("start-row") {
+            config.start_row = Some(v.parse::<u32>().unwrap());
+        }
+        if let Some(v) = matches.value_of("benchmark") {
+            config.benchmark = Some(v.parse::<u32>().unwrap().max(1));
+        }
+        if matches.is_present("single") {
+            config.single = true;
+        }
+        if let Some(v) = matches.value_of("click-offset-x") {
+            config.click_offset_x = parse_click_offset(v);
+        }
+        if let Some(v) = matches.value_of("click-offset-y") {
+            config.click_offset_y = parse_click_offset(v);
+        }
+        if let Some(v) = matches.value_of("info-override") {
+            config.info_override = Some(String::from(v));
+        }
+        if let Some(v) = matches.value_of("grid-rows") {
+            config.grid_rows = Some(v.parse::<u32>().unwrap());
+        }
+        if let Some(v) = matches.value_of("grid-cols") {
+            config.grid_cols = Some(v.parse::<u32>().unwrap());
+        }
+        if matches.is_present("detect-grid") {
+            config.detect_grid = true;
+        }
+        if matches.is_present("single-capture") {
+            config.single_capture = true;
+        }
+
+        // sets may come from the command line or the config file
+        if config.limit_sets_to_scan.is_some() && config.sets.is_none() {
+            utils::error_and_quit("--limit-sets-to-scan需要同时指定--sets");
+        }
+
+        config
+    }
+
+    // modes that only inspect the game and export nothing
+    pub fn is_diagnostic(&self) -> bool {
+        self.debug_regions.is_some() || self.capture_only || self.calibrate || self.single || self.benchmark.is_some()
+    }
+
+    // keys are the field names, e.g. {"min_star": 5, "sets": ["CrimsonWitch"]},
+    // missing keys keep their defaults
+    pub fn from_file(path: &str) -> Result<YasScannerConfig, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("无法读取{}：{}", path, e))?;
+        let mut config: YasScannerConfig = serde_json::from_str(&content)
+            .map_err(|e| format!("配置文件{}无效：{}", path, e))?;
+        config.stop_at_known_run = config.stop_at_known_run.max(1);
+        config.benchmark = config.benchmark.map(|x| x.max(1));
+
+        Ok(config)
+    }
+}
+
+// a fraction of the cell, 1.0 would be on the border to the next cell
+fn is_valid_click_offset(offset: f64) -> bool {
+    (0.0..1.0).contains(&offset)
+}
+
+fn parse_click_offset(s: &str) -> f64 {
+    match s.parse::<f64>() {
+        Ok(offset) if is_valid_click_offset(offset) => offset,
+        _ => utils::error_and_quit("点击位置必须在0到1之间（不含1）"),
+    }
+}
+
+fn deserialize_click_offset<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    let offset = f64::deserialize(d)?;
+    if !is_valid_click_offset(offset) {
+        return Err(de::Error::custom("click offset must be at least 0 and less than 1"));
+    }
+
+    Ok(offset)
+}
+
+// english or chinese names, like --sets
+fn deserialize_sets<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Vec<ArtifactSetName>>, D::Error> {
+    let names: Option<Vec<String>> = Option::deserialize(d)?;
+    match names {
+        Some(names) => names.iter()
+            .map(|name| ArtifactSetName::from_name(name).ok_or_else(|| de::Error::custom(format!("unknown set: {}", name))))
+            .collect::<Result<Vec<ArtifactSetName>, D::Error>>()
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+fn parse_color_metric(s: &str) -> ColorMetric {
+    match ColorMetric::from_name(s) {
+        Some(m) => m,
+        None => utils::error_and_quit(&format!("未知的颜色比较方式：{}，可选：euclidean, redmean", s)),
+    }
+}
+
+// e.g. "CrimsonWitch,炽烈的炎之魔女"
+fn parse_sets(s: &str) -> Vec<ArtifactSetName> {
+    let mut sets: Vec<ArtifactSetName> = Vec::new();
+    for name in s.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        match ArtifactSetName::from_name(name) {
+            Some(set) => sets.push(set),
+            None => {
+                let all = ArtifactSetName::all().iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                utils::error_and_quit(&format!("未知的圣遗物套装：{}，可选：{}", name, all));
+            }
+        }
+    }
+
+    sets
+}
+
+// set one key of a --config file, keeping the others
+fn write_config_value(path: &str, key: &str, value: Value) -> Result<(), String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("无法读取{}：{}", path, e))?;
+    let mut json: Value = serde_json::from_str(&content).map_err(|e| format!("无法解析{}：{}", path, e))?;
+    let obj = json.as_object_mut().ok_or(format!("{}不是json对象", path))?;
+    obj.insert(String::from(key), value);
+
+    let content = serde_json::to_string_pretty(&json).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| format!("无法写入{}：{}", path, e))
+}
+
+fn log_latency(name: &str, times: &[f64]) {
+    let min = times.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = times.iter().cloned().fold(0.0, f64::max);
+    let avg = times.iter().sum::<f64>() / times.len() as f64;
+    info!("{}: min {:.2}ms, avg {:.2}ms, max {:.2}ms", name, min, avg, max);
+}
+
+// the lag in [min, max] where the profile best matches itself shifted,
+// i.e. the distance between repeating grid cells
+fn best_period(profile: &[f64], min: usize, max: usize) -> Option<usize> {
+    let mean = profile.iter().sum::<f64>() / profile.len() as f64;
+    let mut best: Option<(usize, f64)> = None;
+    for lag in min..=max.min(profile.len().saturating_sub(1)) {
+        let n = profile.len() - lag;
+        let r = (0..n).map(|i| (profile[i] - mean) * (profile[i + lag] - mean)).sum::<f64>() / n as f64;
+        if best.map_or(true, |(_, best_r)| r > best_r) {
+            best = Some((lag, r));
         }
     }
+
+    best.map(|(lag, _)| lag)
+}
+
+fn star_from_color(color: &Color) -> u32 {
+    let color_1 = Color::from(113, 119, 139);
+    let color_2 = Color::from(42, 143, 114);
+    let color_3 = Color::from(81, 127, 203);
+    let color_4 = Color::from(161, 86, 224);
+    let color_5 = Color::from(188, 105, 50);
+
+    let mut min_dis: u32 = color_1.dis_2(color);
+    let mut star = 1_u32;
+    if color_2.dis_2(color) < min_dis {
+        star = 2;
+    }
+    if color_3.dis_2(color) < min_dis {
+        star = 3;
+    }
+    if color_4.dis_2(color) < min_dis {
+        star = 4;
+    }
+    if color_5.dis_2(color) < min_dis {
+        star = 5;
+    }
+
+    star
 }
 
 pub struct YasScanner {
     model: CRNNModel,
-    enigo: Enigo,
+    enigo: Box<dyn MouseControllable>,
+    capturer: Box<dyn Capturer>,
+    // None when not scanning a window, e.g. with a MockCapturer
+    hwnd: Option<HWND>,
 
     info: ScanInfo,
     config: YasScannerConfig,
@@ -48,6 +413,9 @@ pub struct YasScanner {
     pool: f64,
 
     initial_color: Color,
+    // the grid background between two cells, what an empty cell looks like.
+    // None when the grid has no gap to sample
+    empty_color: Option<Color>,
 
     // for scrolls
     scrolled_rows: u32,
@@ -55,6 +423,97 @@ pub struct YasScanner {
 
     avg_switch_time: f64,
     scanned_count: u32,
+
+    tearing_count: u32,
+    torn_skipped: u32,
+
+    // with single_capture, the panel captured by the last get_pool
+    last_panel: Option<RawCaptureImage>,
+
+    // for --max-duration
+    start_time: SystemTime,
+
+    status: ScanStatus,
+}
+
+// how a scan ended, ordered by severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ScanStatus {
+    Complete,
+    ParseErrors,
+    ScrollFailed,
+    Interrupted,
+}
+
+impl ScanStatus {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ScanStatus::Complete => exit_code::SUCCESS,
+            ScanStatus::ParseErrors => exit_code::PARSE_ERRORS,
+            ScanStatus::ScrollFailed => exit_code::SCROLL_FAILED,
+            ScanStatus::Interrupted => exit_code::INTERRUPTED,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ScanError {
+    WindowNotFound,
+    // start row, total rows
+    InvalidStartRow(u32, u32),
+    DiagnosticFailed(String),
+    Other(String),
+}
+
+impl ScanError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ScanError::WindowNotFound => exit_code::WINDOW_NOT_FOUND,
+            ScanError::InvalidStartRow(_, _) => exit_code::INVALID_START_ROW,
+            ScanError::DiagnosticFailed(_) => exit_code::DIAGNOSTIC_FAILED,
+            ScanError::Other(_) => exit_code::ERROR,
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScanError::WindowNotFound => write!(f, "未找到原神窗口，请确认原神已经开启"),
+            ScanError::InvalidStartRow(row, total) => write!(f, "起始行{}超出圣遗物总行数{}", row, total),
+            ScanError::DiagnosticFailed(s) => write!(f, "{}", s),
+            ScanError::Other(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl From<String> for ScanError {
+    fn from(s: String) -> ScanError {
+        ScanError::Other(s)
+    }
+}
+
+// timing collected while scanning, see YasScanner::stats
+#[derive(Clone, Debug)]
+pub struct ScanStats {
+    pub scrolled_rows: u32,
+    // mouse wheel ticks per row
+    pub avg_scroll_one_row: f64,
+    // ms
+    pub avg_switch_time: f64,
+    // switches included in avg_switch_time
+    pub scanned_count: u32,
+    pub tearing_count: u32,
+    // items skipped because the panel never settled
+    pub torn_skipped: u32,
+}
+
+// what the library entry points return
+#[derive(Debug)]
+pub struct ScanReport {
+    pub artifacts: Vec<InternalArtifact>,
+    pub status: ScanStatus,
+    pub stats: ScanStats,
 }
 
 #[derive(Debug)]
@@ -69,9 +528,15 @@ pub struct YasScanResult {
     level: String,
     equip: String,
     star: u32,
+    // per field, in the order above
+    confidence: [f64; 9],
 }
 
 impl YasScanResult {
+    pub fn min_confidence(&self) -> f64 {
+        self.confidence.iter().cloned().fold(1.0, f64::min)
+    }
+
     pub fn to_internal_artifact(&self) -> Option<InternalArtifact> {
         let set_name = ArtifactSetName::from_zh_cn(&self.name)?;
         let slot = ArtifactSlot::from_zh_cn(&self.name)?;
@@ -110,19 +575,100 @@ impl YasScanResult {
     }
 }
 
+// captures are BGRA, sum all three color channels so that items differing
+// only in one channel are still told apart
 fn calc_pool(row: &Vec<u8>) -> f64 {
     let len = row.len() / 4;
     let mut pool: f64 = 0.0;
 
     for i in 0..len {
-        pool += row[i * 4] as f64;
+        pool += row[i * 4] as f64 + row[i * 4 + 1] as f64 + row[i * 4 + 2] as f64;
     }
     pool /= len as f64;
     pool
 }
 
+// average color of a BGRA capture
+fn mean_color(data: &[u8]) -> Color {
+    let len = data.len() / 4;
+    if len == 0 {
+        return Color::new();
+    }
+    let mut sum = [0_u64; 3];
+    for i in 0..len {
+        sum[0] += data[i * 4 + 2] as u64;
+        sum[1] += data[i * 4 + 1] as u64;
+        sum[2] += data[i * 4] as u64;
+    }
+
+    Color((sum[0] / len as u64) as u8, (sum[1] / len as u64) as u8, (sum[2] / len as u64) as u8)
+}
+
+// the count in the bag header, e.g. "圣遗物 123/1500". counts past the
+// inventory limit are misreads
+fn parse_art_count(s: &str) -> Option<u32> {
+    if !s.starts_with("圣遗物") {
+        return None;
+    }
+    let chars = s.chars().collect::<Vec<char>>();
+    if chars.len() < 10 {
+        return None;
+    }
+    let count_str = (&chars[4..chars.len() - 5]).iter().collect::<String>();
+    let count = count_str.parse::<u32>().ok()?;
+    if count > MAX_ART_COUNT {
+        warn!("detected count {} exceeds the inventory limit {}", count, MAX_ART_COUNT);
+        return None;
+    }
+
+    Some(count)
+}
+
+// absolute, where get_pool captures
+fn pool_rect(info: &ScanInfo) -> PixelRect {
+    PixelRect {
+        left: info.left as i32 + info.pool_position.left,
+        top: info.top as i32 + info.pool_position.top,
+        width: info.pool_position.right - info.pool_position.left,
+        height: info.pool_position.bottom - info.pool_position.top,
+    }
+}
+
+fn panel_rect(info: &ScanInfo) -> PixelRect {
+    PixelRect {
+        left: info.left as i32 + info.panel_position.left,
+        top: info.top as i32 + info.panel_position.top,
+        width: info.panel_position.right - info.panel_position.left,
+        height: info.panel_position.bottom - info.panel_position.top,
+    }
+}
+
+// share of pixels that are not the same color in two captures of a rect
+fn changed_ratio(a: &RawCaptureImage, b: &RawCaptureImage, metric: ColorMetric, threshold: u32) -> f64 {
+    if a.w != b.w || a.h != b.h || a.w == 0 || a.h == 0 {
+        return 1.0;
+    }
+
+    let mut changed = 0;
+    for y in 0..a.h {
+        for x in 0..a.w {
+            if !a.get_color(x, y).is_same_with(&b.get_color(x, y), metric, threshold) {
+                changed += 1;
+            }
+        }
+    }
+    changed as f64 / (a.w * a.h) as f64
+}
+
 impl YasScanner {
-    pub fn new(info: ScanInfo, config: YasScannerConfig) -> YasScanner {
+    pub fn new(hwnd: HWND, info: ScanInfo, config: YasScannerConfig) -> YasScanner {
+        let mut scanner = YasScanner::with_capturer(info, config, Box::new(ScreenCapturer), Box::new(Enigo::new()));
+        scanner.hwnd = Some(hwnd);
+        scanner
+    }
+
+    // scan without a game window, the focus is never checked
+    pub fn with_capturer(info: ScanInfo, config: YasScannerConfig, capturer: Box<dyn Capturer>, mouse: Box<dyn MouseControllable>) -> YasScanner {
         let row = info.art_row;
         let col = info.art_col;
 
@@ -131,7 +677,9 @@ impl YasScanner {
                 String::from("model_training.onnx"),
                 String::from("index_2_word.json")
             ),
-            enigo: Enigo::new(),
+            enigo: mouse,
+            capturer,
+            hwnd: None,
             info,
             config,
 
@@ -140,11 +688,41 @@ impl YasScanner {
 
             pool: -1.0,
             initial_color: Color::new(),
+            empty_color: None,
             scrolled_rows: 0,
             avg_scroll_one_row: 0.0,
 
             avg_switch_time: 0.0,
             scanned_count: 0,
+
+            tearing_count: 0,
+            torn_skipped: 0,
+
+            last_panel: None,
+
+            start_time: SystemTime::now(),
+
+            status: ScanStatus::Complete,
+        }
+    }
+
+    pub fn status(&self) -> ScanStatus {
+        self.status
+    }
+
+    // keep the most severe status seen
+    fn set_status(&mut self, status: ScanStatus) {
+        self.status = self.status.max(status);
+    }
+
+    pub fn stats(&self) -> ScanStats {
+        ScanStats {
+            scrolled_rows: self.scrolled_rows,
+            avg_scroll_one_row: self.avg_scroll_one_row,
+            avg_switch_time: self.avg_switch_time,
+            scanned_count: self.scanned_count,
+            tearing_count: self.tearing_count,
+            torn_skipped: self.torn_skipped,
         }
     }
 }
@@ -152,45 +730,153 @@ impl YasScanner {
 impl YasScanner {
     pub fn move_to(&mut self, row: u32, col: u32) {
         let info = &self.info;
-        let left = info.left + info.left_margin + (info.art_width + info.art_gap_x) * col + info.art_width / 2;
-        let top = info.top + info.top_margin + (info.art_height + info.art_gap_y) * row + info.art_height / 4;
+        let offset_x = (info.art_width as f64 * self.config.click_offset_x) as u32;
+        let offset_y = (info.art_height as f64 * self.config.click_offset_y) as u32;
+        let left = info.left + info.left_margin + (info.art_width + info.art_gap_x) * col + offset_x;
+        let top = info.top + info.top_margin + (info.art_height + info.art_gap_y) * row + offset_y;
         self.enigo.mouse_move_to(left as i32, top as i32);
     }
 
-    fn sample_initial_color(&mut self) {
-        self.initial_color = self.get_color();
+    // block while the game window is not in the foreground, so that clicks
+    // never land on another window
+    // fails once the window is closed or --max-duration passes while waiting
+    fn wait_for_focus(&self) -> Result<(), String> {
+        let hwnd = match self.hwnd {
+            Some(h) => h,
+            None => return Ok(()),
+        };
+        if utils::is_foreground(hwnd) {
+            return Ok(());
+        }
+
+        warn!("原神窗口不在前台，扫描已暂停");
+        while !utils::is_foreground(hwnd) {
+            if !utils::is_window(hwnd) {
+                return Err(String::from("原神窗口已关闭"));
+            }
+            self.check_deadline()?;
+            utils::sleep(500);
+        }
+        info!("原神窗口已回到前台，继续扫描");
+        utils::sleep(500);
+
+        Ok(())
+    }
+
+    fn check_deadline(&self) -> Result<(), String> {
+        match self.config.max_duration {
+            Some(seconds) if self.start_time.elapsed().unwrap().as_secs() >= seconds as u64 => {
+                Err(format!("超过最大扫描时间{}秒", seconds))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    // captures fail transiently, e.g. while a notification covers the window.
+    // retry with an increasing delay before giving up
+    fn retry_capture<T, F: FnMut() -> Result<T, String>>(&self, mut f: F) -> Result<T, String> {
+        let mut delay = CAPTURE_RETRY_DELAY;
+        let mut attempt = 0;
+        loop {
+            match f() {
+                Ok(v) => return Ok(v),
+                Err(e) => {
+                    if attempt >= self.config.capture_retry {
+                        return Err(e);
+                    }
+                    attempt += 1;
+                    warn!("capture failed: {}, retry {} in {}ms", e, attempt, delay);
+                    utils::sleep(delay);
+                    delay = (delay * 2).min(MAX_CAPTURE_RETRY_DELAY);
+                }
+            }
+        }
+    }
+
+    fn is_same_color(&self, a: &Color, b: &Color) -> bool {
+        let metric = self.config.color_metric;
+        let threshold = self.config.color_threshold.unwrap_or(metric.default_threshold());
+        a.is_same_with(b, metric, threshold)
+    }
+
+    fn sample_initial_color(&mut self) -> Result<(), String> {
+        self.initial_color = self.get_color()?;
+        Ok(())
+    }
+
+    // middle of the gap right of the first cell, at half the cell height
+    fn empty_sample_rect(&self) -> Option<PixelRect> {
+        let info = &self.info;
+        if info.art_gap_x == 0 || info.art_height < 2 {
+            return None;
+        }
+
+        Some(PixelRect {
+            left: (info.left + info.left_margin + info.art_width) as i32,
+            top: (info.top + info.top_margin + info.art_height / 4) as i32,
+            width: info.art_gap_x as i32,
+            height: (info.art_height / 2) as i32,
+        })
+    }
+
+    // the same size as the gap sample, centered on the cell
+    fn cell_sample_rect(&self, row: u32, col: u32) -> Option<PixelRect> {
+        let info = &self.info;
+        let gap = self.empty_sample_rect()?;
+        let left = info.left + info.left_margin + (info.art_width + info.art_gap_x) * col + (info.art_width - info.art_gap_x.min(info.art_width)) / 2;
+        let top = info.top + info.top_margin + (info.art_height + info.art_gap_y) * row + info.art_height / 4;
+
+        Some(PixelRect {
+            left: left as i32,
+            top: top as i32,
+            width: gap.width,
+            height: gap.height,
+        })
+    }
+
+    fn sample_empty_color(&mut self) -> Result<(), String> {
+        self.empty_color = match self.empty_sample_rect() {
+            Some(rect) => {
+                let data = self.retry_capture(|| self.capturer.capture_absolute(&rect))?;
+                Some(mean_color(&data))
+            },
+            None => None,
+        };
+        Ok(())
+    }
+
+    // whether the cell shows only the grid background, i.e. holds no artifact
+    fn is_empty_cell(&self, row: u32, col: u32) -> Result<bool, String> {
+        let (empty, rect) = match (&self.empty_color, self.cell_sample_rect(row, col)) {
+            (Some(c), Some(r)) => (c, r),
+            _ => return Ok(false),
+        };
+        let data = self.retry_capture(|| self.capturer.capture_absolute(&rect))?;
+
+        Ok(self.is_same_color(&mean_color(&data), empty))
     }
 
-    fn get_color(&self) -> Color {
+    fn get_color(&self) -> Result<Color, String> {
         let flag_x = self.info.flag_x + self.info.left;
         let flag_y = self.info.flag_y + self.info.top;
-        let color = capture::get_color(flag_x, flag_y);
+        let color = self.retry_capture(|| self.capturer.get_color(flag_x, flag_y))?;
 
-        color
+        Ok(color)
     }
 
     fn get_art_count(&mut self) -> Result<u32, String> {
         let info = &self.info;
-        let raw_after_pp = self.info.art_count_position.capture_relative(info).unwrap();
+        let raw_after_pp = self.info.art_count_position.capture_relative_with(info, self.capturer.as_ref())?;
         raw_after_pp.to_gray_image().save("count.png");
         let s = self.model.inference_string(&raw_after_pp);
         info!("raw count string: {}", s);
-        if s.starts_with("圣遗物") {
-            let chars = s.chars().collect::<Vec<char>>();
-            let count_str = (&chars[4..chars.len() - 5]).iter().collect::<String>();
-            let count = match count_str.parse::<u32>() {
-                Ok(v) => v,
-                Err(_) => {
-                    return Err(String::from("无法识别圣遗物数量"));
-                }
-            };
-            return Ok(count);
+        match parse_art_count(&s) {
+            Some(v) => Ok(v),
+            None => Err(String::from("无法识别圣遗物数量")),
         }
-
-        Err(String::from("无法识别圣遗物数量"))
     }
 
-    fn scroll_one_row(&mut self) -> bool {
+    fn scroll_one_row(&mut self) -> Result<bool, String> {
         let mut state = 0;
         let mut count = 0;
         let max_scroll = 20;
@@ -198,45 +884,91 @@ impl YasScanner {
             self.enigo.mouse_scroll_y(-5);
             utils::sleep(80);
             count += 1;
-            let color: Color = self.get_color();
+            let color: Color = self.get_color()?;
             // println!("{:?}", color);
-            if state == 0 && !color.is_same(&self.initial_color) {
+            if state == 0 && !self.is_same_color(&color, &self.initial_color) {
                 state = 1;
-            } else if state == 1 && self.initial_color.is_same(&color) {
+            } else if state == 1 && self.is_same_color(&self.initial_color, &color) {
                 self.avg_scroll_one_row = (self.avg_scroll_one_row * self.scrolled_rows as f64 + count as f64) / (self.scrolled_rows as f64 + 1.0);
                 info!("avg scroll/row: {}", self.avg_scroll_one_row);
                 self.scrolled_rows += 1;
-                return true;
+                return Ok(true);
             }
         }
 
-        false
+        Ok(false)
     }
 
-    fn scroll_rows(&mut self, count: u32) {
+    fn scroll_rows(&mut self, count: u32) -> Result<(), String> {
         if self.scrolled_rows >= 5 {
-            let scroll = ((self.avg_scroll_one_row * count as f64 - 3.0).round() as u32).max(0);
+            let scroll = ((self.avg_scroll_one_row * count as f64).round() as u32).saturating_sub(self.short_ticks());
             for _ in 0..scroll {
                 self.enigo.mouse_scroll_y(-1);
             }
             utils::sleep(400);
-            self.align_row();
-            return;
+            if !self.align_row()? {
+                warn!("cannot align the row after scrolling");
+                self.set_status(ScanStatus::ScrollFailed);
+            }
+            return Ok(());
         }
 
         for _ in 0..count {
-            if !self.scroll_one_row() {
+            if !self.scroll_one_row()? {
+                warn!("scrolling one row timed out");
+                self.set_status(ScanStatus::ScrollFailed);
                 break;
             }
         }
+
+        Ok(())
+    }
+
+    // scroll past the first `rows` rows, returns the row on screen where
+    // scanning continues, since the list cannot scroll past its last page
+    fn skip_rows(&mut self, rows: u32, total_row: u32) -> Result<u32, String> {
+        let scroll = rows.min(total_row.saturating_sub(self.row));
+        self.scroll_rows(scroll)?;
+        utils::sleep(100);
+
+        Ok(rows - scroll)
+    }
+
+    fn is_aligned(&self) -> Result<bool, String> {
+        let color = self.get_color()?;
+        Ok(self.is_same_color(&color, &self.initial_color))
+    }
+
+    // ticks to scroll one row, 10 until a row has been measured
+    fn row_ticks(&self) -> u32 {
+        if self.scrolled_rows > 0 {
+            (self.avg_scroll_one_row.ceil() as u32).max(1)
+        } else {
+            10
+        }
     }
 
-    fn align_row(&mut self) -> bool {
+    fn short_ticks(&self) -> u32 {
+        SCROLL_SHORT_TICKS.min(self.row_ticks() - 1)
+    }
+
+    // scroll_rows stops a few ticks short on purpose, so look downward first,
+    // but only as far as it stopped short. if the row is not found there the
+    // estimate overshot, search upward
+    fn align_row(&mut self) -> Result<bool, String> {
+        let row_ticks = self.row_ticks();
+        let down_ticks = self.short_ticks();
+
         let mut count = 0;
-        while count < 10 {
-            let color = self.get_color();
-            if color.is_same(&self.initial_color) {
-                return true;
+        loop {
+            if self.is_aligned()? {
+                if count > 0 {
+                    info!("aligned after scrolling down {} ticks", count);
+                }
+                return Ok(true);
+            }
+            if count >= down_ticks {
+                break;
             }
 
             self.enigo.mouse_scroll_y(-1);
@@ -244,25 +976,69 @@ impl YasScanner {
             count += 1;
         }
 
-        false
+        // back to where the search started, then less than one row above it,
+        // so that an already scanned row is never aligned to
+        warn!("row not aligned after scrolling down {} ticks, scrolling up", count);
+        let mut up = 0;
+        while up < count + row_ticks - 1 {
+            self.enigo.mouse_scroll_y(1);
+            utils::sleep(50);
+            up += 1;
+
+            if self.is_aligned()? {
+                info!("aligned after scrolling up {} ticks", up);
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn get_pool(&mut self) -> Result<f64, String> {
+        if self.config.single_capture {
+            let panel = self.capture_panel()?;
+            if let Some(pool) = self.get_pool_in_panel(&panel) {
+                self.last_panel = Some(panel);
+                return Ok(pool);
+            }
+        }
+
+        self.capture_pool()
     }
 
-    fn wait_until_switched(&mut self) -> bool {
+    fn capture_pool(&self) -> Result<f64, String> {
+        let rect = pool_rect(&self.info);
+        let im = self.retry_capture(|| self.capturer.capture_absolute(&rect))?;
+
+        Ok(calc_pool(&im))
+    }
+
+    // None when the pool is not inside the panel, e.g. with --info-override
+    fn get_pool_in_panel(&self, panel: &RawCaptureImage) -> Option<f64> {
+        let p = &self.info.panel_position;
+        let pool = &self.info.pool_position;
+        if pool.left < p.left || pool.right > p.right || pool.top < p.top || pool.bottom > p.bottom {
+            return None;
+        }
+
+        let rect = PixelRect {
+            left: pool.left - p.left,
+            top: pool.top - p.top,
+            width: pool.right - pool.left,
+            height: pool.bottom - pool.top,
+        };
+        Some(calc_pool(&panel.crop(&rect).data))
+    }
+
+    fn wait_until_switched(&mut self) -> Result<bool, String> {
         let now = SystemTime::now();
         while now.elapsed().unwrap().as_millis() < self.config.max_wait_switch_artifact as u128 {
             // let pool_start = SystemTime::now();
-            let rect = PixelRect {
-                left: self.info.left as i32 + self.info.pool_position.left,
-                top: self.info.top as i32 + self.info.pool_position.top,
-                width: self.info.pool_position.right - self.info.pool_position.left,
-                height: self.info.pool_position.bottom - self.info.pool_position.top,
-            };
-            let im = capture::capture_absolute(&rect).unwrap();
-            let pool = calc_pool(&im);
+            let pool = self.get_pool()?;
             // info!("pool: {}", pool);
             // println!("pool time: {}ms", pool_start.elapsed().unwrap().as_millis());
 
-            if (pool - self.pool).abs() > 0.000001 {
+            if (pool - self.pool).abs() > self.config.pool_threshold {
                 // if pool != pool1 {
                 //     pool1 = pool;
                 // } else {
@@ -272,67 +1048,74 @@ impl YasScanner {
                 self.avg_switch_time = (self.avg_switch_time * self.scanned_count as f64 + now.elapsed().unwrap().as_millis() as f64) / (self.scanned_count as f64 + 1.0);
                 self.scanned_count += 1;
                 // info!("avg switch time: {}ms", self.avg_switch_time);
-                return true;
+                return Ok(true);
             }
         }
 
-        false
+        Ok(false)
     }
 
     fn capture_panel(&mut self) -> Result<RawCaptureImage, String> {
         let now = SystemTime::now();
-        let w = self.info.panel_position.right - self.info.panel_position.left;
-        let h = self.info.panel_position.bottom - self.info.panel_position.top;
-        let rect: PixelRect = PixelRect {
-            left: self.info.left as i32 + self.info.panel_position.left,
-            top: self.info.top as i32 + self.info.panel_position.top,
-            width: w,
-            height: h,
-        };
-        let u8_arr = capture::capture_absolute(&rect)?;
+        let rect = panel_rect(&self.info);
+        let u8_arr = self.retry_capture(|| self.capturer.capture_absolute(&rect))?;
         // info!("capture time: {}ms", now.elapsed().unwrap().as_millis());
         Ok(RawCaptureImage {
             data: u8_arr,
-            w: w as u32,
-            h: h as u32,
+            w: rect.width as u32,
+            h: rect.height as u32,
         })
     }
 
-    fn get_star(&self) -> u32 {
-        let color = capture::get_color(
+    // only accept a capture once two consecutive ones agree, so a panel
+    // still in its switching animation is not recognized. None if the
+    // panel keeps changing
+    fn capture_panel_stable(&mut self) -> Result<Option<RawCaptureImage>, String> {
+        let metric = self.config.color_metric;
+        let threshold = self.config.color_threshold.unwrap_or(metric.default_threshold());
+        let mut capture = self.capture_panel()?;
+        for _ in 0..MAX_TEARING_RETRY {
+            utils::sleep(TEARING_CHECK_DELAY);
+            let next = self.capture_panel()?;
+            if changed_ratio(&capture, &next, metric, threshold) < TEARING_TOLERANCE {
+                return Ok(Some(next));
+            }
+
+            self.tearing_count += 1;
+            capture = next;
+        }
+
+        warn!("panel still changing after {} captures", MAX_TEARING_RETRY + 1);
+        Ok(None)
+    }
+
+    fn get_star(&self) -> Result<u32, String> {
+        let color = self.retry_capture(|| self.capturer.get_color(
             self.info.star_x + self.info.left,
             self.info.star_y + self.info.top
-        );
+        ))?;
 
-        let color_1 = Color::from(113, 119, 139);
-        let color_2 = Color::from(42, 143, 114);
-        let color_3 = Color::from(81, 127, 203);
-        let color_4 = Color::from(161, 86, 224);
-        let color_5 = Color::from(188, 105, 50);
+        Ok(star_from_color(&color))
+    }
 
-        let mut min_dis: u32 = color_1.dis_2(&color);
-        let mut star = 1_u32;
-        if color_2.dis_2(&color) < min_dis {
-            star = 2;
-        }
-        if color_3.dis_2(&color) < min_dis {
-            star = 3;
-        }
-        if color_4.dis_2(&color) < min_dis {
-            star = 4;
-        }
-        if color_5.dis_2(&color) < min_dis {
-            star = 5;
+    // read the star color from the panel capture when it covers the star
+    // position, saving one capture per item
+    fn get_star_in_panel(&self, panel: &RawCaptureImage) -> Result<u32, String> {
+        let p = &self.info.panel_position;
+        let (x, y) = (self.info.star_x as i32, self.info.star_y as i32);
+        if x < p.left || x >= p.right || y < p.top || y >= p.bottom {
+            return self.get_star();
         }
 
-        star
+        let color = panel.get_color((x - p.left) as u32, (y - p.top) as u32);
+        Ok(star_from_color(&color))
     }
 
-    fn start_capture_only(&mut self) {
+    fn start_capture_only(&mut self) -> Result<(), String> {
         fs::create_dir("captures");
         let info = &self.info.clone();
 
-        let count = self.info.art_count_position.capture_relative(info).unwrap();
+        let count = self.info.art_count_position.capture_relative_with(info, self.capturer.as_ref())?;
         count.to_gray_image().save("captures/count.png");
 
         let convert_rect = |rect: &PixelRectBound| {
@@ -344,7 +1127,7 @@ impl YasScanner {
             }
         };
 
-        let panel = self.capture_panel().unwrap();
+        let panel = self.capture_panel()?;
         let im_title = panel.crop_and_preprocess(&convert_rect(&info.title_position));
         im_title.to_gray_image().save("captures/title.png");
         let im_main_stat_name = panel.crop_and_preprocess(&convert_rect(&info.main_stat_name_position));
@@ -363,23 +1146,409 @@ impl YasScanner {
         im_level.to_gray_image().save("captures/level.png");
         let im_equip = panel.crop_and_preprocess(&convert_rect(&info.equip_position));
         im_equip.to_gray_image().save("captures/equip.png");
+
+        Ok(())
     }
 
-    pub fn start(&mut self) -> Vec<InternalArtifact> {
-        if self.config.capture_only {
-            self.start_capture_only();
-            return Vec::new();
+    // measure the distance between cells on a capture of the grid, and
+    // replace the gaps when they are off by more than GRID_GAP_TOLERANCE
+    fn detect_grid_gap(&mut self) -> Result<(), String> {
+        let info = &self.info;
+        let pitch_x = info.art_width + info.art_gap_x;
+        let pitch_y = info.art_height + info.art_gap_y;
+        let width = (pitch_x * info.art_col).min(info.width - info.left_margin);
+        let height = (pitch_y * info.art_row).min(info.height - info.top_margin);
+        let rect = PixelRect {
+            left: (info.left + info.left_margin) as i32,
+            top: (info.top + info.top_margin) as i32,
+            width: width as i32,
+            height: height as i32,
+        };
+        let img = self.capturer.capture_absolute_image(&rect)?;
+
+        let mut profile_x = vec![0.0; width as usize];
+        let mut profile_y = vec![0.0; height as usize];
+        for (x, y, p) in img.enumerate_pixels() {
+            let gray = (p[0] as f64 + p[1] as f64 + p[2] as f64) / 3.0;
+            profile_x[x as usize] += gray / height as f64;
+            profile_y[y as usize] += gray / width as f64;
         }
 
-        let mut count = match self.get_art_count() {
-            Ok(v) => v,
-            Err(_) => 1000,
-        };
+        let search = |pitch: u32| ((pitch as f64 * 0.8) as usize, (pitch as f64 * 1.2) as usize);
+        let (min_x, max_x) = search(pitch_x);
+        let (min_y, max_y) = search(pitch_y);
+        let measured_x = best_period(&profile_x, min_x, max_x).ok_or(String::from("grid too small to measure"))? as u32;
+        let measured_y = best_period(&profile_y, min_y, max_y).ok_or(String::from("grid too small to measure"))? as u32;
+        info!("grid pitch measured: {}x{}, configured: {}x{}", measured_x, measured_y, pitch_x, pitch_y);
 
-        let total_row = (count + self.col - 1) / self.col;
-        let last_row_col = if count % self.col == 0 {
-            self.col
-        } else {
+        if (measured_x as i32 - pitch_x as i32).abs() > GRID_GAP_TOLERANCE && measured_x > self.info.art_width {
+            self.info.art_gap_x = measured_x - self.info.art_width;
+            info!("using measured horizontal gap: {}", self.info.art_gap_x);
+        }
+        if (measured_y as i32 - pitch_y as i32).abs() > GRID_GAP_TOLERANCE && measured_y > self.info.art_height {
+            self.info.art_gap_y = measured_y - self.info.art_height;
+            info!("using measured vertical gap: {}", self.info.art_gap_y);
+        }
+
+        Ok(())
+    }
+
+    fn start_debug_regions(&self, path: &str) -> Result<(), String> {
+        let info = &self.info;
+        let rect = PixelRect {
+            left: info.left as i32,
+            top: info.top as i32,
+            width: info.width as i32,
+            height: info.height as i32,
+        };
+        let mut img = self.capturer.capture_absolute_image(&rect)?;
+        info.draw_regions(&mut img);
+
+        img.save(path).map_err(|e| e.to_string())?;
+        info!("regions saved to {}", path);
+        Ok(())
+    }
+
+    // switch through the first row with a generous timeout, and suggest a
+    // max-wait-switch-artifact value from the measured latency
+    fn start_calibrate(&mut self) -> Result<(), String> {
+        self.move_to(0, 0);
+        self.enigo.mouse_click(MouseButton::Left);
+        utils::sleep(1000);
+        self.pool = self.get_pool()?;
+
+        self.config.max_wait_switch_artifact = 3000;
+        let mut max_switch_time = 0_u128;
+        let mut switched = 0_u32;
+        for col in 1..self.col {
+            self.move_to(0, col);
+            let now = SystemTime::now();
+            self.enigo.mouse_click(MouseButton::Left);
+
+            if !self.wait_until_switched()? {
+                warn!("calibrate: item {} did not switch within {}ms", col, self.config.max_wait_switch_artifact);
+                continue;
+            }
+            let t = now.elapsed().unwrap().as_millis();
+            info!("calibrate: item {} switched in {}ms", col, t);
+            max_switch_time = max_switch_time.max(t);
+            switched += 1;
+        }
+
+        if switched == 0 {
+            return Err(String::from("未检测到圣遗物切换"));
+        }
+
+        // leave some headroom above the slowest switch observed
+        let suggested = ((max_switch_time as f64 * 1.5).ceil() as u32).max(10);
+        info!("avg switch time: {}ms", self.avg_switch_time);
+        info!("max switch time: {}ms", max_switch_time);
+        match self.config.config_file {
+            Some(ref path) => {
+                write_config_value(path, "max_wait_switch_artifact", Value::from(suggested))?;
+                info!("已将max_wait_switch_artifact={}写入{}", suggested, path);
+            },
+            None => {
+                info!("建议参数：--max-wait-switch-artifact={}，指定--config时会写入配置文件", suggested);
+            }
+        }
+
+        Ok(())
+    }
+
+    // recognize the selected artifact once without touching the mouse, and
+    // log both the raw strings and the parsed artifact
+    fn start_single(&mut self) -> Result<(), String> {
+        let info = self.info.clone();
+        let panel = self.capture_panel()?;
+        let star = self.get_star_in_panel(&panel)?;
+
+        let convert_rect = |rect: &PixelRectBound| {
+            PixelRect {
+                left: rect.left - info.panel_position.left,
+                top: rect.top - info.panel_position.top,
+                width: rect.right - rect.left,
+                height: rect.bottom - rect.top,
+            }
+        };
+        let model = &mut self.model;
+        let mut read = |rect: &PixelRectBound| model.inference_string_with_confidence(&panel.crop_and_preprocess(&convert_rect(rect)));
+
+        let (name, conf_name) = read(&info.title_position);
+        let (main_stat_name, conf_main_stat_name) = read(&info.main_stat_name_position);
+        let (main_stat_value, conf_main_stat_value) = read(&info.main_stat_value_position);
+        let (sub_stat_1, conf_sub_stat_1) = read(&info.sub_stat1_position);
+        let (sub_stat_2, conf_sub_stat_2) = read(&info.sub_stat2_position);
+        let (sub_stat_3, conf_sub_stat_3) = read(&info.sub_stat3_position);
+        let (sub_stat_4, conf_sub_stat_4) = read(&info.sub_stat4_position);
+        let (level, conf_level) = read(&info.level_position);
+        let (equip, conf_equip) = read(&info.equip_position);
+
+        let result = YasScanResult {
+            name,
+            main_stat_name,
+            main_stat_value,
+            sub_stat_1,
+            sub_stat_2,
+            sub_stat_3,
+            sub_stat_4,
+            level,
+            equip,
+            star,
+            confidence: [
+                conf_name, conf_main_stat_name, conf_main_stat_value,
+                conf_sub_stat_1, conf_sub_stat_2, conf_sub_stat_3, conf_sub_stat_4,
+                conf_level, conf_equip,
+            ],
+        };
+        info!("raw: {:?}", result);
+        match result.to_internal_artifact() {
+            Some(a) => info!("parsed: {:?}", a),
+            None => warn!("cannot parse the artifact"),
+        }
+
+        Ok(())
+    }
+
+    // capture and recognize the selected artifact repeatedly without moving the mouse
+    fn start_benchmark(&mut self, times: u32) -> Result<(), String> {
+        let info = self.info.clone();
+
+        let convert_rect = |rect: &PixelRectBound| {
+            PixelRect {
+                left: rect.left - info.panel_position.left,
+                top: rect.top - info.panel_position.top,
+                width: rect.right - rect.left,
+                height: rect.bottom - rect.top,
+            }
+        };
+        let positions = [
+            &info.title_position,
+            &info.main_stat_name_position,
+            &info.main_stat_value_position,
+            &info.sub_stat1_position,
+            &info.sub_stat2_position,
+            &info.sub_stat3_position,
+            &info.sub_stat4_position,
+            &info.level_position,
+            &info.equip_position,
+        ];
+
+        let mut capture_times: Vec<f64> = Vec::new();
+        let mut pool_times: Vec<f64> = Vec::new();
+        let mut pool_in_panel_times: Vec<f64> = Vec::new();
+        let mut star_times: Vec<f64> = Vec::new();
+        let mut star_in_panel_times: Vec<f64> = Vec::new();
+        let mut inference_times: Vec<f64> = Vec::new();
+        for _ in 0..times {
+            let now = SystemTime::now();
+            let panel = self.capture_panel()?;
+            capture_times.push(now.elapsed().unwrap().as_micros() as f64 / 1000.0);
+
+            let now = SystemTime::now();
+            let pool = self.capture_pool()?;
+            pool_times.push(now.elapsed().unwrap().as_micros() as f64 / 1000.0);
+
+            let now = SystemTime::now();
+            let pool_in_panel = self.get_pool_in_panel(&panel);
+            pool_in_panel_times.push(now.elapsed().unwrap().as_micros() as f64 / 1000.0);
+            if pool_in_panel.map_or(false, |x| (x - pool).abs() > self.config.pool_threshold) {
+                warn!("pool mismatch: captured {}, from panel {:?}", pool, pool_in_panel);
+            }
+
+            let now = SystemTime::now();
+            let star = self.get_star()?;
+            star_times.push(now.elapsed().unwrap().as_micros() as f64 / 1000.0);
+
+            let now = SystemTime::now();
+            let star_in_panel = self.get_star_in_panel(&panel)?;
+            star_in_panel_times.push(now.elapsed().unwrap().as_micros() as f64 / 1000.0);
+            if star != star_in_panel {
+                warn!("star mismatch: captured {}, from panel {}", star, star_in_panel);
+            }
+
+            let now = SystemTime::now();
+            for rect in positions.iter() {
+                self.model.inference_string(&panel.crop_and_preprocess(&convert_rect(rect)));
+            }
+            inference_times.push(now.elapsed().unwrap().as_micros() as f64 / 1000.0);
+        }
+
+        info!("benchmark: {} runs", times);
+        log_latency("capture", &capture_times);
+        log_latency("pool", &pool_times);
+        log_latency("pool from panel", &pool_in_panel_times);
+        log_latency("star", &star_times);
+        log_latency("star from panel", &star_in_panel_times);
+        log_latency("inference", &inference_times);
+
+        // the captures one switch needs besides the inference
+        let total = |parts: &[&Vec<f64>]| (0..times as usize).map(|i| parts.iter().map(|x| x[i]).sum()).collect::<Vec<f64>>();
+        log_latency("separate captures", &total(&[&pool_times, &capture_times, &star_times]));
+        log_latency("single capture (--single-capture)", &total(&[&capture_times, &pool_in_panel_times, &star_in_panel_times]));
+
+        Ok(())
+    }
+
+    fn scan_loop(&mut self, tx: &mpsc::Sender<Option<(RawCaptureImage, u32)>>, count: u32, total_row: u32, last_row_col: u32, skip_count: u32) -> Result<(), String> {
+        let mut scanned_row = 0_u32;
+        // rows scanned in this run for --max-row, skipped rows do not count
+        let mut run_row = 0_u32;
+        let mut scanned_count = 0_u32;
+        let mut start_row = 0_u32;
+        self.start_time = SystemTime::now();
+
+        self.move_to(0, 0);
+        self.enigo.mouse_click(MouseButton::Left);
+        utils::sleep(1000);
+        self.sample_initial_color()?;
+        self.sample_empty_color()?;
+        // the first artifact is selected now, switches are detected relative to it
+        self.pool = self.get_pool()?;
+
+        if skip_count > 0 {
+            let skip_row = skip_count / self.col;
+            start_row = self.skip_rows(skip_row, total_row)?;
+            scanned_row = skip_row;
+            scanned_count = skip_count;
+        }
+
+        while scanned_count < count {
+            'row: for row in start_row..self.row {
+                let c = if scanned_row == total_row - 1 { last_row_col } else { self.col };
+                'col: for col in 0..c {
+                    if scanned_count > count {
+                        return Ok(());
+                    }
+
+                    self.check_deadline()?;
+                    self.wait_for_focus()?;
+                    self.move_to(row, col);
+                    self.enigo.mouse_click(MouseButton::Left);
+
+                    // already selected by the initial click, it would never switch
+                    let switched = if row == 0 && col == 0 && scanned_count == 0 {
+                        true
+                    } else {
+                        self.wait_until_switched()?
+                    };
+                    // an off count leaves empty cells at the end of the bag, clicking
+                    // one keeps the previous artifact selected. a cell that still
+                    // holds an artifact is only slow to show, wait for it once more
+                    if !switched && scanned_count > 0 {
+                        if self.is_empty_cell(row, col)? {
+                            info!("row {} column {} is empty, stopping", row, col);
+                            return Ok(());
+                        }
+                        if !self.wait_until_switched()? {
+                            warn!("row {} column {} did not switch", row, col);
+                        }
+                    }
+
+                    let last_panel = self.last_panel.take();
+                    let capture = if self.config.check_tearing {
+                        match self.capture_panel_stable()? {
+                            Some(c) => c,
+                            None => {
+                                error!("skipping row {} column {}, the panel kept changing", row, col);
+                                self.torn_skipped += 1;
+                                self.set_status(ScanStatus::ParseErrors);
+                                scanned_count += 1;
+                                continue;
+                            }
+                        }
+                    } else {
+                        match last_panel {
+                            Some(panel) => panel,
+                            None => self.capture_panel()?,
+                        }
+                    };
+                    let star = self.get_star_in_panel(&capture)?;
+                    if star < self.config.min_star {
+                        return Ok(());
+                    }
+                    if tx.send(Some((capture, star))).is_err() {
+                        // the recognizer stopped early, e.g. on repeated duplicates
+                        return Ok(());
+                    }
+
+                    scanned_count += 1;
+                } // end 'col
+
+                scanned_row += 1;
+                run_row += 1;
+
+                if run_row >= self.config.max_row {
+                    info!("max row reached, quiting...");
+                    return Ok(());
+                }
+            } // end 'row
+
+            let remain = count - scanned_count;
+            let remain_row = (remain + self.col - 1) / self.col;
+            let scroll_row = remain_row.min(self.row);
+            start_row = self.row - scroll_row;
+            self.check_deadline()?;
+            self.wait_for_focus()?;
+            self.scroll_rows(scroll_row)?;
+
+            utils::sleep(100);
+        }
+
+        Ok(())
+    }
+
+    // run the debug mode selected in the config, nothing is recognized for export
+    pub fn start_diagnostic(&mut self) -> Result<(), String> {
+        if let Some(path) = self.config.debug_regions.clone() {
+            return self.start_debug_regions(&path).map_err(|e| format!("保存截图失败：{}", e));
+        }
+
+        if self.config.capture_only {
+            return self.start_capture_only().map_err(|e| format!("保存截图失败：{}", e));
+        }
+
+        if self.config.calibrate {
+            return self.start_calibrate().map_err(|e| format!("校准失败：{}", e));
+        }
+
+        if self.config.single {
+            return self.start_single().map_err(|e| format!("识别失败：{}", e));
+        }
+
+        if let Some(times) = self.config.benchmark {
+            return self.start_benchmark(times).map_err(|e| format!("测试失败：{}", e));
+        }
+
+        Err(String::from("未指定调试模式"))
+    }
+
+    pub fn start(&mut self) -> Result<Vec<InternalArtifact>, ScanError> {
+        if self.config.detect_grid {
+            if let Err(e) = self.detect_grid_gap() {
+                warn!("cannot measure the grid: {}, using the configured gaps", e);
+            }
+        }
+
+        let count = match self.get_art_count() {
+            Ok(v) => v,
+            Err(_) => 1000,
+        };
+
+        self.start_with_count(count)
+    }
+
+    // scans the first `count` items, an empty bag returns before touching the mouse
+    fn start_with_count(&mut self, count: u32) -> Result<Vec<InternalArtifact>, ScanError> {
+        if count == 0 {
+            info!("no artifacts to scan");
+            return Ok(Vec::new());
+        }
+
+        let total_row = (count + self.col - 1) / self.col;
+        let last_row_col = if count % self.col == 0 {
+            self.col
+        } else {
             count % self.col
         };
 
@@ -394,19 +1563,70 @@ impl YasScanner {
         info!("total row: {}", total_row);
         info!("last column: {}", last_row_col);
 
+        let mut skip_count = 0_u32;
+        let mut initial_results: Vec<InternalArtifact> = Vec::new();
+        if self.config.resume {
+            match checkpoint::load_checkpoint(checkpoint::CHECKPOINT_PATH) {
+                Ok(c) => {
+                    if c.count != count {
+                        warn!("圣遗物数量与上次扫描时不一致（{} -> {}），背包可能已改变，继续扫描的结果可能不准确", c.count, count);
+                    }
+                    info!("resume from checkpoint: {} scanned, {} results", c.processed, c.results.len());
+                    skip_count = c.processed.min(count) / self.col * self.col;
+                    initial_results = c.results;
+                },
+                Err(e) => {
+                    warn!("cannot load checkpoint: {}, scanning from the beginning", e);
+                }
+            }
+        } else if let Some(start_row) = self.config.start_row {
+            if start_row >= total_row {
+                return Err(ScanError::InvalidStartRow(start_row, total_row));
+            }
+            info!("start from row {}", start_row);
+            skip_count = start_row * self.col;
+        }
+
+        let mut known: Vec<InternalArtifact> = Vec::new();
+        if let Some(ref path) = self.config.stop_at_known {
+            match MonaFormat::load(path.clone()) {
+                Ok(v) => {
+                    info!("loaded {} known artifacts from {}", v.len(), path);
+                    known = v;
+                },
+                Err(e) => {
+                    warn!("cannot load known artifacts: {}, scanning everything", e);
+                }
+            }
+        }
+        let known_run = self.config.stop_at_known_run;
+        let min_confidence = self.config.min_confidence;
+        let target_sets = match self.config.limit_sets_to_scan {
+            Some(limit) => self.config.sets.clone().map(|sets| (sets, limit)),
+            None => None,
+        };
+
         let (tx, rx) = mpsc::channel::<Option<(RawCaptureImage, u32)>>();
         let info_2 = self.info.clone();
+        let col = self.col;
         // v bvvmnvbm
         let handle = thread::spawn(move || {
-            let mut results: Vec<InternalArtifact> = Vec::new();
+            let mut results: Vec<InternalArtifact> = initial_results;
             let mut model = CRNNModel::new(
                 String::from("model_training.onnx"),
                 String::from("index_2_word.json")
             );
             let mut error_count = 0;
             let mut dup_count = 0;
-            let mut hash = HashSet::new();
+            let mut hash: HashSet<InternalArtifact> = results.iter().cloned().collect();
+            let mut processed = skip_count;
             let mut consecutive_dup_count = 0;
+            // mona exports do not record equip, compare without it
+            let known_set: HashSet<InternalArtifact> = known.iter().map(|a| a.without_equip()).collect();
+            let mut consecutive_known_count = 0;
+            let mut stopped_at_known = false;
+            let mut scroll_failed = false;
+            let mut target_count = 0_u32;
             let info = info_2;
 
             let convert_rect = |rect: &PixelRectBound| {
@@ -424,15 +1644,15 @@ impl YasScanner {
                     None => break,
                 };
                 let now = SystemTime::now();
-                let str_title = model.inference_string(&capture.crop_and_preprocess(&convert_rect(&info.title_position)));
-                let str_main_stat_name = model.inference_string(&capture.crop_and_preprocess(&convert_rect(&info.main_stat_name_position)));
-                let str_main_stat_value = model.inference_string(&capture.crop_and_preprocess(&convert_rect(&info.main_stat_value_position)));
-                let str_sub_stat_1 = model.inference_string(&capture.crop_and_preprocess(&convert_rect(&info.sub_stat1_position)));
-                let str_sub_stat_2 = model.inference_string(&capture.crop_and_preprocess(&convert_rect(&info.sub_stat2_position)));
-                let str_sub_stat_3 = model.inference_string(&capture.crop_and_preprocess(&convert_rect(&info.sub_stat3_position)));
-                let str_sub_stat_4 = model.inference_string(&capture.crop_and_preprocess(&convert_rect(&info.sub_stat4_position)));
-                let str_level = model.inference_string(&capture.crop_and_preprocess(&convert_rect(&info.level_position)));
-                let str_equip = model.inference_string(&capture.crop_and_preprocess(&convert_rect(&info.equip_position)));
+                let (str_title, conf_title) = model.inference_string_with_confidence(&capture.crop_and_preprocess(&convert_rect(&info.title_position)));
+                let (str_main_stat_name, conf_main_stat_name) = model.inference_string_with_confidence(&capture.crop_and_preprocess(&convert_rect(&info.main_stat_name_position)));
+                let (str_main_stat_value, conf_main_stat_value) = model.inference_string_with_confidence(&capture.crop_and_preprocess(&convert_rect(&info.main_stat_value_position)));
+                let (str_sub_stat_1, conf_sub_stat_1) = model.inference_string_with_confidence(&capture.crop_and_preprocess(&convert_rect(&info.sub_stat1_position)));
+                let (str_sub_stat_2, conf_sub_stat_2) = model.inference_string_with_confidence(&capture.crop_and_preprocess(&convert_rect(&info.sub_stat2_position)));
+                let (str_sub_stat_3, conf_sub_stat_3) = model.inference_string_with_confidence(&capture.crop_and_preprocess(&convert_rect(&info.sub_stat3_position)));
+                let (str_sub_stat_4, conf_sub_stat_4) = model.inference_string_with_confidence(&capture.crop_and_preprocess(&convert_rect(&info.sub_stat4_position)));
+                let (str_level, conf_level) = model.inference_string_with_confidence(&capture.crop_and_preprocess(&convert_rect(&info.level_position)));
+                let (str_equip, conf_equip) = model.inference_string_with_confidence(&capture.crop_and_preprocess(&convert_rect(&info.equip_position)));
                 let predict_time = now.elapsed().unwrap().as_millis();
                 // println!("predict time: {}ms", predict_time);
 
@@ -447,16 +1667,43 @@ impl YasScanner {
                     level: str_level,
                     equip: str_equip,
                     star,
+                    confidence: [
+                        conf_title, conf_main_stat_name, conf_main_stat_value,
+                        conf_sub_stat_1, conf_sub_stat_2, conf_sub_stat_3, conf_sub_stat_4,
+                        conf_level, conf_equip,
+                    ],
                 };
                 // println!("{:?}", result);
-                let art = result.to_internal_artifact();
+                let art = if result.min_confidence() < min_confidence {
+                    error!("low confidence detection: {:?}", result);
+                    None
+                } else {
+                    result.to_internal_artifact()
+                };
                 if let Some(a) = art {
+                    let (min_sub, max_sub) = expected_sub_stat_count(a.star, a.level);
+                    let sub_count = a.sub_stat_count();
+                    if sub_count < min_sub || sub_count > max_sub {
+                        warn!("expected {}~{} sub stats but got {}, possibly a wrong detection: {:?}", min_sub, max_sub, sub_count, result);
+                    }
+
+                    if known_set.contains(&a.without_equip()) {
+                        consecutive_known_count += 1;
+                    } else {
+                        consecutive_known_count = 0;
+                    }
+
                     if hash.contains(&a) {
                         dup_count += 1;
                         consecutive_dup_count += 1;
                         warn!("dup artifact detected: {:?}", result);
                     } else {
                         consecutive_dup_count = 0;
+                        if let Some((ref sets, _)) = target_sets {
+                            if sets.contains(&a.set_name) {
+                                target_count += 1;
+                            }
+                        }
                         hash.insert(a.clone());
                         results.push(a);
                     }
@@ -465,73 +1712,444 @@ impl YasScanner {
                     error_count += 1;
                     // println!("error parsing results");
                 }
+
+                processed += 1;
+                if processed % (col * checkpoint::CHECKPOINT_INTERVAL_ROWS) == 0 {
+                    if let Err(e) = checkpoint::save_checkpoint(checkpoint::CHECKPOINT_PATH, count, processed, &results) {
+                        warn!("cannot save checkpoint: {}", e);
+                    }
+                }
+
                 if consecutive_dup_count >= info.art_row {
                     error!("检测到连续多个重复圣遗物，可能为翻页错误，或者为非背包顶部开始扫描");
+                    scroll_failed = true;
                     break;
                 }
+                if let Some((_, limit)) = target_sets {
+                    if target_count >= limit {
+                        info!("collected {} artifacts of the target sets, stopping", target_count);
+                        break;
+                    }
+                }
+                if known_run > 0 && !known_set.is_empty() && consecutive_known_count >= known_run {
+                    info!("{} known artifacts in a row, stopping", consecutive_known_count);
+                    stopped_at_known = true;
+                    break;
+                }
+            }
+
+            // the rest of the inventory is assumed unchanged since the previous export
+            if stopped_at_known {
+                let before = results.len();
+                let mut seen: HashSet<InternalArtifact> = results.iter().map(|a| a.without_equip()).collect();
+                for a in known.into_iter() {
+                    if seen.insert(a.without_equip()) {
+                        results.push(a);
+                    }
+                }
+                info!("merged {} artifacts from the previous export", results.len() - before);
             }
 
             info!("error count: {}", error_count);
             info!("dup count: {}", dup_count);
 
-            results
+            (results, error_count, scroll_failed)
         });
 
 
-        let mut scanned_row = 0_u32;
-        let mut scanned_count = 0_u32;
-        let mut start_row = 0_u32;
+        if let Err(e) = self.scan_loop(&tx, count, total_row, last_row_col, skip_count) {
+            error!("扫描中断：{}，将导出已识别的圣遗物", e);
+            self.set_status(ScanStatus::Interrupted);
+        }
 
-        self.move_to(0, 0);
-        self.enigo.mouse_click(MouseButton::Left);
-        utils::sleep(1000);
-        self.sample_initial_color();
+        tx.send(None).ok();
 
-        'outer: while scanned_count < count {
-            'row: for row in start_row..self.row {
-                let c = if scanned_row == total_row - 1 { last_row_col } else { self.col };
-                'col: for col in 0..c {
-                    if scanned_count > count {
-                        break 'outer;
-                    }
+        if self.config.check_tearing {
+            info!("tearing detected: {} times, {} items skipped", self.tearing_count, self.torn_skipped);
+        }
+        info!("扫描结束，等待识别线程结束，请勿关闭程序");
+        let (mut results, error_count, scroll_failed) = handle.join().unwrap();
+        info!("count: {}", results.len());
+        if error_count > 0 {
+            self.set_status(ScanStatus::ParseErrors);
+        }
+        if scroll_failed {
+            self.set_status(ScanStatus::ScrollFailed);
+        }
+        // keep the checkpoint of an unfinished scan for --resume
+        if self.status == ScanStatus::Complete {
+            fs::remove_file(checkpoint::CHECKPOINT_PATH).ok();
+        } else {
+            info!("scan not complete, keeping {} for --resume", checkpoint::CHECKPOINT_PATH);
+        }
 
-                    self.move_to(row, col);
-                    self.enigo.mouse_click(MouseButton::Left);
+        let min_level = self.config.min_level;
+        let before = results.len();
+        results.retain(|a| a.level >= min_level);
+        if results.len() < before {
+            info!("removed {} artifacts below level {}", before - results.len(), min_level);
+        }
 
-                    self.wait_until_switched();
+        if let Some(ref sets) = self.config.sets {
+            results.retain(|a| sets.contains(&a.set_name));
+            info!("{} artifacts matched the set filter", results.len());
+        }
 
-                    let capture = self.capture_panel().unwrap();
-                    let star = self.get_star();
-                    if star < self.config.min_star {
-                        break 'outer;
-                    }
-                    tx.send(Some((capture, star))).unwrap();
+        if let Some(equipped) = self.config.equipped {
+            let equipped_count = results.iter().filter(|a| a.equip.is_some()).count();
+            info!("equipped: {}, unequipped: {}", equipped_count, results.len() - equipped_count);
+            results.retain(|a| a.equip.is_some() == equipped);
+        }
 
-                    scanned_count += 1;
-                } // end 'col
+        if self.config.sort {
+            results.sort_by(|a, b| a.export_order(b));
+        }
 
-                scanned_row += 1;
+        summary::log_main_stat_distribution(&results);
 
-                if scanned_row >= self.config.max_row {
-                    info!("max row reached, quiting...");
-                    break 'outer;
-                }
-            } // end 'row
+        Ok(results)
+    }
+}
 
-            let remain = count - scanned_count;
-            let remain_row = (remain + self.col - 1) / self.col;
-            let scroll_row = remain_row.min(self.row);
-            start_row = self.row - scroll_row;
-            self.scroll_rows(scroll_row);
+// find the game window and scan the whole inventory, independent of the
+// command line. e.g.
+//
+//     let config = YasScannerConfig { min_star: 5, ..Default::default() };
+//     let artifacts = yas::scanner::yas_scanner::scan(config)?;
+pub fn scan(config: YasScannerConfig) -> Result<Vec<InternalArtifact>, String> {
+    scan_report(config)
+        .map(|report| report.artifacts)
+        .map_err(|e| e.to_string())
+}
 
-            utils::sleep(100);
+// like scan, also telling how the scan ended and how long switches and
+// scrolls took
+pub fn scan_report(config: YasScannerConfig) -> Result<ScanReport, ScanError> {
+    if config.is_diagnostic() {
+        return Err(ScanError::Other(String::from("调试模式不导出圣遗物，请使用diagnose")));
+    }
+
+    let mut scanner = open_scanner(config)?;
+    let artifacts = scanner.start()?;
+
+    Ok(ScanReport {
+        artifacts,
+        status: scanner.status(),
+        stats: scanner.stats(),
+    })
+}
+
+// run the debug mode set in the config, e.g. single or calibrate
+pub fn diagnose(config: YasScannerConfig) -> Result<(), ScanError> {
+    let mut scanner = open_scanner(config)?;
+    scanner.start_diagnostic().map_err(ScanError::DiagnosticFailed)
+}
+
+// find the game window and build a scanner for its layout
+fn open_scanner(config: YasScannerConfig) -> Result<YasScanner, ScanError> {
+    unsafe { SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE); }
+    let hwnd = match utils::find_window(String::from("原神")) {
+        Ok(h) => h,
+        Err(_) => return Err(ScanError::WindowNotFound),
+    };
+
+    unsafe { ShowWindow(hwnd, SW_RESTORE); }
+    unsafe { SetForegroundWindow(hwnd); }
+    utils::sleep(1000);
+
+    let rect = utils::get_client_rect(hwnd)?;
+    info!("detected left: {}", rect.left);
+    info!("detected top: {}", rect.top);
+    info!("detected width: {}", rect.width);
+    info!("detected height: {}", rect.height);
+
+    let mut info = ScanInfo::from_rect(&rect)?;
+    if let Some(ref path) = config.info_override {
+        let content = fs::read_to_string(path).map_err(|e| format!("无法读取{}：{}", path, e))?;
+        let json: Value = serde_json::from_str(&content).map_err(|e| format!("无法解析{}：{}", path, e))?;
+        info.apply_override(&json).map_err(|e| format!("位置覆盖无效：{}", e))?;
+        info!("applied layout override from {}", path);
+    }
+    if let Some(rows) = config.grid_rows {
+        if rows == 0 {
+            return Err(ScanError::Other(String::from("--grid-rows必须大于0")));
         }
+        info!("grid rows overridden: {} -> {}", info.art_row, rows);
+        info.art_row = rows;
+    }
+    if let Some(cols) = config.grid_cols {
+        if cols == 0 {
+            return Err(ScanError::Other(String::from("--grid-cols必须大于0")));
+        }
+        info!("grid columns overridden: {} -> {}", info.art_col, cols);
+        info.art_col = cols;
+    }
 
-        tx.send(None).unwrap();
+    Ok(YasScanner::new(hwnd, info, config))
+}
 
-        info!("扫描结束，等待识别线程结束，请勿关闭程序");
-        let results: Vec<InternalArtifact> = handle.join().unwrap();
-        info!("count: {}", results.len());
-        results
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use crate::capture::mock::MockCapturer;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum MouseEvent {
+        Move(i32, i32),
+        Click,
+        Scroll(i32),
+    }
+
+    struct MockMouse {
+        events: Rc<RefCell<Vec<MouseEvent>>>,
+    }
+
+    impl MouseControllable for MockMouse {
+        fn mouse_move_to(&mut self, x: i32, y: i32) {
+            self.events.borrow_mut().push(MouseEvent::Move(x, y));
+        }
+
+        fn mouse_move_relative(&mut self, _x: i32, _y: i32) {}
+
+        fn mouse_down(&mut self, _button: MouseButton) {}
+
+        fn mouse_up(&mut self, _button: MouseButton) {}
+
+        fn mouse_click(&mut self, _button: MouseButton) {
+            self.events.borrow_mut().push(MouseEvent::Click);
+        }
+
+        fn mouse_scroll_x(&mut self, _length: i32) {}
+
+        fn mouse_scroll_y(&mut self, length: i32) {
+            self.events.borrow_mut().push(MouseEvent::Scroll(length));
+        }
+    }
+
+    fn grid_info(rows: u32, cols: u32) -> ScanInfo {
+        let mut info = ScanInfo::from_rect(&PixelRect { left: 0, top: 0, width: 1920, height: 1080 }).unwrap();
+        info.art_row = rows;
+        info.art_col = cols;
+        info
+    }
+
+    // a pool capture, every value gives a different pool
+    fn pool_frame(info: &ScanInfo, value: u8) -> Vec<u8> {
+        let rect = pool_rect(info);
+        vec![value; (rect.width * rect.height * 4) as usize]
+    }
+
+    fn mock_scanner(info: ScanInfo, config: YasScannerConfig, capturer: MockCapturer) -> (YasScanner, Rc<RefCell<Vec<MouseEvent>>>) {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mouse = MockMouse { events: events.clone() };
+        let scanner = YasScanner::with_capturer(info, config, Box::new(capturer), Box::new(mouse));
+        (scanner, events)
+    }
+
+    fn test_config() -> YasScannerConfig {
+        YasScannerConfig {
+            min_star: 1,
+            max_wait_switch_artifact: 100,
+            ..Default::default()
+        }
     }
-}
\ No newline at end of file
+
+    // runs scan_loop over the first `count` items, returns how many were
+    // handed to the recognizer
+    fn run_scan_loop(scanner: &mut YasScanner, count: u32) -> Result<usize, String> {
+        run_scan_loop_from(scanner, count, 0)
+    }
+
+    fn run_scan_loop_from(scanner: &mut YasScanner, count: u32, skip_count: u32) -> Result<usize, String> {
+        let total_row = (count + scanner.col - 1) / scanner.col;
+        let last_row_col = if count % scanner.col == 0 { scanner.col } else { count % scanner.col };
+        let (tx, rx) = mpsc::channel::<Option<(RawCaptureImage, u32)>>();
+        scanner.scan_loop(&tx, count, total_row, last_row_col, skip_count)?;
+        drop(tx);
+
+        Ok(rx.iter().filter(|x| x.is_some()).count())
+    }
+
+    #[test]
+    fn test_scan_loop_scripted_items() {
+        let info = grid_info(1, 3);
+        let capturer = MockCapturer::new();
+        let rect = pool_rect(&info);
+        for value in [10, 20, 30].iter() {
+            capturer.push_frame(&rect, pool_frame(&info, *value));
+        }
+
+        let (mut scanner, events) = mock_scanner(info, test_config(), capturer);
+        assert_eq!(run_scan_loop(&mut scanner, 3), Ok(3));
+        // the first item is selected by the initial click and never waited for
+        assert_eq!(scanner.stats().scanned_count, 2);
+        let clicks = events.borrow().iter().filter(|e| **e == MouseEvent::Click).count();
+        assert_eq!(clicks, 4);
+    }
+
+    #[test]
+    fn test_stats_averages_update() {
+        let info = grid_info(1, 1);
+        let capturer = MockCapturer::new();
+        let rect = pool_rect(&info);
+        capturer.push_frame(&rect, pool_frame(&info, 10));
+        capturer.push_frame(&rect, pool_frame(&info, 20));
+        // the row flag leaves and comes back on the second scroll tick
+        let (flag_x, flag_y) = (info.left + info.flag_x, info.top + info.flag_y);
+        capturer.push_color(flag_x, flag_y, &Color(200, 200, 200));
+        capturer.push_color(flag_x, flag_y, &Color(0, 0, 0));
+        capturer.push_color(flag_x, flag_y, &Color(200, 200, 200));
+
+        let (mut scanner, _) = mock_scanner(info, test_config(), capturer);
+        assert_eq!(scanner.stats().scanned_count, 0);
+        assert_eq!(run_scan_loop(&mut scanner, 2), Ok(2));
+
+        let stats = scanner.stats();
+        assert_eq!(stats.scrolled_rows, 1);
+        assert_eq!(stats.avg_scroll_one_row, 2.0);
+        assert_eq!(stats.scanned_count, 1);
+        assert!(stats.avg_switch_time >= 0.0 && stats.avg_switch_time < 100.0);
+    }
+
+    #[test]
+    fn test_calc_pool_all_channels() {
+        // BGRA, the two rows only differ in blue
+        let a = vec![10, 100, 100, 255, 10, 100, 100, 255];
+        let b = vec![90, 100, 100, 255, 90, 100, 100, 255];
+        assert_eq!(calc_pool(&a), 210.0);
+        assert_eq!(calc_pool(&b), 290.0);
+        assert_ne!(calc_pool(&a), calc_pool(&b));
+    }
+
+    #[test]
+    fn test_parse_art_count() {
+        assert_eq!(parse_art_count("圣遗物 123/1500"), Some(123));
+        assert_eq!(parse_art_count("圣遗物 0/1500"), Some(0));
+        assert_eq!(parse_art_count("圣遗物 1500/1500"), Some(1500));
+        assert_eq!(parse_art_count("圣遗物 1501/1500"), None);
+        assert_eq!(parse_art_count("圣遗物 l23/1500"), None);
+        assert_eq!(parse_art_count("圣遗物"), None);
+        assert_eq!(parse_art_count("武器 123/2000"), None);
+    }
+
+    #[test]
+    fn test_empty_bag_no_mouse() {
+        let (mut scanner, events) = mock_scanner(grid_info(5, 8), test_config(), MockCapturer::new());
+        assert!(scanner.start_with_count(0).unwrap().is_empty());
+        assert!(events.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_count_off_by_two_stops_at_empty_cell() {
+        let info = grid_info(1, 5);
+        let capturer = MockCapturer::new();
+        let rect = pool_rect(&info);
+        // only three artifacts, the last two cells are empty and keep the third
+        // selected. they capture as black, like the grid background
+        for value in [10, 20, 30].iter() {
+            capturer.push_frame(&rect, pool_frame(&info, *value));
+        }
+
+        let (mut scanner, _) = mock_scanner(info, test_config(), capturer);
+        assert_eq!(run_scan_loop(&mut scanner, 5), Ok(3));
+    }
+
+    #[test]
+    fn test_first_item_not_waited_for() {
+        let info = grid_info(1, 5);
+        let capturer = MockCapturer::new();
+        // the pool never changes, any wait for the first item would time out
+        capturer.push_frame(&pool_rect(&info), pool_frame(&info, 10));
+        let config = YasScannerConfig {
+            max_wait_switch_artifact: 10000,
+            ..test_config()
+        };
+
+        let (mut scanner, _) = mock_scanner(info, config, capturer);
+        let now = SystemTime::now();
+        assert_eq!(run_scan_loop(&mut scanner, 1), Ok(1));
+        assert!(now.elapsed().unwrap().as_millis() < 5000);
+        assert_eq!(scanner.stats().scanned_count, 0);
+    }
+
+    #[test]
+    fn test_align_row_overshoot_scrolls_up() {
+        let info = grid_info(5, 1);
+        let (flag_x, flag_y) = (info.left + info.flag_x, info.top + info.flag_y);
+        let capturer = MockCapturer::new();
+        // 5 ticks per row. the estimate overshot the row by one tick, so the
+        // next row is 4 ticks down: the 4 downward probes and the first 3
+        // ticks back up miss, the 4th tick up is the row
+        for _ in 0..7 {
+            capturer.push_color(flag_x, flag_y, &Color(0, 0, 0));
+        }
+        capturer.push_color(flag_x, flag_y, &Color(200, 200, 200));
+
+        let (mut scanner, events) = mock_scanner(info, test_config(), capturer);
+        scanner.initial_color = Color(200, 200, 200);
+        scanner.avg_scroll_one_row = 5.0;
+        scanner.scrolled_rows = 5;
+        scanner.scroll_rows(1).unwrap();
+
+        let scrolls: Vec<i32> = events.borrow().iter().filter_map(|e| match e {
+            MouseEvent::Scroll(x) => Some(*x),
+            _ => None,
+        }).collect();
+        // 2 ticks of estimate, 3 down, then 4 up: one tick above where the estimate ended
+        assert_eq!(scrolls.len(), 9);
+        assert_eq!(scrolls[2..].iter().sum::<i32>(), 1);
+        assert_eq!(scanner.status, ScanStatus::Complete);
+    }
+
+    #[test]
+    fn test_max_row_counts_rows_of_this_run() {
+        // the whole bag fits on screen, skipping 2 rows starts at the third
+        let info = grid_info(5, 1);
+        let capturer = MockCapturer::new();
+        let rect = pool_rect(&info);
+        for value in [10, 20, 30, 40].iter() {
+            capturer.push_frame(&rect, pool_frame(&info, *value));
+        }
+        let config = YasScannerConfig {
+            max_row: 2,
+            ..test_config()
+        };
+
+        let (mut scanner, _) = mock_scanner(info, config, capturer);
+        assert_eq!(run_scan_loop_from(&mut scanner, 5, 2), Ok(2));
+    }
+
+    #[test]
+    fn test_slow_switch_on_last_row_is_not_empty() {
+        let info = grid_info(1, 3);
+        let (probe, _) = mock_scanner(info.clone(), test_config(), MockCapturer::new());
+        let cell = probe.cell_sample_rect(0, 2).unwrap();
+
+        let capturer = MockCapturer::new();
+        let rect = pool_rect(&info);
+        // the third artifact never shows within the wait, but its cell is not
+        // the black grid background
+        for value in [10, 20].iter() {
+            capturer.push_frame(&rect, pool_frame(&info, *value));
+        }
+        capturer.push_frame(&cell, [40, 160, 220, 255].repeat((cell.width * cell.height) as usize));
+
+        let (mut scanner, _) = mock_scanner(info, test_config(), capturer);
+        assert_eq!(run_scan_loop(&mut scanner, 3), Ok(3));
+    }
+
+    #[test]
+    fn test_click_offset_range() {
+        assert!(is_valid_click_offset(0.0));
+        assert!(is_valid_click_offset(0.999));
+        assert!(!is_valid_click_offset(1.0));
+        assert!(!is_valid_click_offset(-0.01));
+        assert!(!is_valid_click_offset(f64::NAN));
+
+        assert!(serde_json::from_str::<YasScannerConfig>(r#"{"click_offset_x": 0.0}"#).is_ok());
+        assert!(serde_json::from_str::<YasScannerConfig>(r#"{"click_offset_x": 1.0}"#).is_err());
+        assert!(serde_json::from_str::<YasScannerConfig>(r#"{"click_offset_y": 1.0}"#).is_err());
+    }
+}